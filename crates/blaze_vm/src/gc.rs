@@ -0,0 +1,251 @@
+/*
+   Copyright 2021 BlazifyOrg
+   Licensed under the Apache License, Version 2.0 (the "License");
+   you may not use this file except in compliance with the License.
+   You may obtain a copy of the License at
+       http://www.apache.org/licenses/LICENSE-2.0
+   Unless required by applicable law or agreed to in writing, software
+   distributed under the License is distributed on an "AS IS" BASIS,
+   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+   See the License for the specific language governing permissions and
+   limitations under the License.
+*/
+
+// Values are reference counted, which frees everything except cycles like an instance
+// whose methods see a scope holding the instance. So every value that can hold other
+// values and every scope is tracked here, and a collection subtracts the references
+// they hold to each other from their reference counts. Whatever has references left is
+// used from outside (a running VM or the Rust side), everything it can't reach is only
+// kept alive by cycles and gets cleared, which frees it.
+
+use crate::{Iter, Konstants, Scope, ScopeSlots, K, VM};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::{Rc, Weak};
+
+const MIN_THRESHOLD: usize = 10_000;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct HeapStats {
+    pub allocated: usize,
+    pub freed: usize,
+    pub collections: usize,
+    pub live: usize,
+}
+
+struct Heap {
+    values: Vec<Weak<RefCell<Konstants>>>,
+    scopes: Vec<Weak<ScopeSlots>>,
+    since_collection: usize,
+    threshold: usize,
+    stress: bool,
+    stats: HeapStats,
+}
+
+thread_local! {
+    static HEAP: RefCell<Heap> = RefCell::new(Heap {
+        values: vec![],
+        scopes: vec![],
+        since_collection: 0,
+        threshold: MIN_THRESHOLD,
+        stress: false,
+        stats: HeapStats::default(),
+    });
+}
+
+// Collects on every allocation, which is slow but makes a missing reference show up
+// right where it happens instead of whenever the next collection runs
+pub fn set_stress(stress: bool) {
+    HEAP.with(|heap| heap.borrow_mut().stress = stress);
+}
+
+// Values freed by their reference count stay in the heap as dead entries until the next
+// collection, so what's live is counted when asked for
+pub fn stats() -> HeapStats {
+    HEAP.with(|heap| {
+        let heap = heap.borrow();
+        let live = heap.values.iter().filter(|v| v.strong_count() > 0).count()
+            + heap.scopes.iter().filter(|s| s.strong_count() > 0).count();
+        HeapStats { live, ..heap.stats }
+    })
+}
+
+pub(crate) fn track_value(k: &K) {
+    let tracked = matches!(
+        *k.borrow(),
+        Konstants::Array(_)
+            | Konstants::Object(..)
+            | Konstants::Map(_)
+            | Konstants::Function(..)
+            | Konstants::Class(_)
+            | Konstants::Accessor(..)
+            | Konstants::Iterator(_)
+            | Konstants::Variant(..)
+    );
+    if tracked {
+        HEAP.with(|heap| heap.borrow_mut().values.push(Rc::downgrade(k)));
+        allocated();
+    }
+}
+
+pub(crate) fn track_scope(scope: &Scope) {
+    HEAP.with(|heap| heap.borrow_mut().scopes.push(Rc::downgrade(scope)));
+    allocated();
+}
+
+fn allocated() {
+    let due = HEAP.with(|heap| {
+        let mut heap = heap.borrow_mut();
+        heap.stats.allocated += 1;
+        heap.since_collection += 1;
+        heap.stress || heap.since_collection >= heap.threshold
+    });
+    if due {
+        collect();
+    }
+}
+
+fn addr<T: ?Sized>(rc: &Rc<T>) -> usize {
+    Rc::as_ptr(rc) as *const u8 as usize
+}
+
+fn vm_refs(vm: &VM, out: &mut Vec<usize>) {
+    out.extend(vm.stack.iter().map(addr));
+    out.extend(vm.symbols.iter().map(addr));
+    out.push(addr(&vm.return_val));
+}
+
+fn value_refs(k: &Konstants, out: &mut Vec<usize>) {
+    match k {
+        Konstants::Array(values) | Konstants::Variant(_, _, values) => {
+            out.extend(values.iter().map(addr))
+        }
+        Konstants::Object(props, class) => {
+            out.extend(props.values().chain(class.iter()).map(addr));
+        }
+        Konstants::Map(map) => out.extend(map.entries().map(|(_, v)| addr(v))),
+        Konstants::Function(_, vm) => vm_refs(vm, out),
+        Konstants::Class(c) => {
+            out.push(addr(&c.statics));
+            out.extend(c.parent.iter().map(addr));
+            vm_refs(&c.vm, out);
+        }
+        Konstants::Accessor(getter, setter) => {
+            out.extend(getter.iter().chain(setter.iter()).map(addr));
+        }
+        Konstants::Iterator(Iter::Values(values, _)) => out.extend(values.iter().map(addr)),
+        Konstants::Iterator(Iter::Object(obj)) => out.push(addr(obj)),
+        _ => (),
+    }
+}
+
+// Runs a collection and returns how many values and scopes it freed
+pub fn collect() -> usize {
+    let (values, scopes) = HEAP.with(|heap| {
+        let mut heap = heap.borrow_mut();
+        (
+            std::mem::take(&mut heap.values),
+            std::mem::take(&mut heap.scopes),
+        )
+    });
+    let values = values.iter().filter_map(Weak::upgrade).collect::<Vec<K>>();
+    let scopes = scopes
+        .iter()
+        .filter_map(Weak::upgrade)
+        .collect::<Vec<Scope>>();
+
+    let mut index = HashMap::new();
+    let mut refs = vec![];
+    for (i, node) in values
+        .iter()
+        .map(|v| (addr(v), Rc::strong_count(v)))
+        .chain(scopes.iter().map(|s| (addr(s), Rc::strong_count(s))))
+        .enumerate()
+    {
+        index.insert(node.0, i);
+        // the upgraded reference held here doesn't count
+        refs.push(node.1 - 1);
+    }
+
+    // Something borrowed right now is in use, and as its references can't be read
+    // whatever it refers to keeps its count and stays alive too
+    let mut edges: Vec<Option<Vec<usize>>> = vec![];
+    for v in &values {
+        edges.push(v.try_borrow().ok().map(|k| {
+            let mut out = vec![];
+            value_refs(&k, &mut out);
+            out
+        }));
+    }
+    for s in &scopes {
+        edges.push(
+            s.try_borrow()
+                .ok()
+                .map(|slots| slots.values().map(|(k, _)| addr(k)).collect::<Vec<usize>>()),
+        );
+    }
+    let edges = edges
+        .into_iter()
+        .map(|e| e.map(|e| e.iter().filter_map(|a| index.get(a).cloned()).collect()))
+        .collect::<Vec<Option<Vec<usize>>>>();
+    for children in edges.iter().flatten() {
+        for child in children {
+            refs[*child] -= 1;
+        }
+    }
+
+    let mut reachable = vec![false; refs.len()];
+    let mut pending = (0..refs.len())
+        .filter(|i| refs[*i] > 0 || edges[*i].is_none())
+        .collect::<Vec<usize>>();
+    while let Some(i) = pending.pop() {
+        if reachable[i] {
+            continue;
+        }
+        reachable[i] = true;
+        if let Some(children) = &edges[i] {
+            pending.extend(children.iter().filter(|c| !reachable[**c]));
+        }
+    }
+
+    // Clearing drops the references that make up the cycles, the contents are only
+    // dropped once nothing is borrowed anymore
+    let (mut cleared, mut cleared_slots) = (vec![], vec![]);
+    let mut freed = 0;
+    let (mut live_values, mut live_scopes) = (vec![], vec![]);
+    for (i, v) in values.iter().enumerate() {
+        if reachable[i] {
+            live_values.push(Rc::downgrade(v));
+        } else if let Ok(mut k) = v.try_borrow_mut() {
+            cleared.push(std::mem::replace(&mut *k, Konstants::None));
+            freed += 1;
+        }
+    }
+    for (i, s) in scopes.iter().enumerate() {
+        if reachable[values.len() + i] {
+            live_scopes.push(Rc::downgrade(s));
+        } else if let Ok(mut slots) = s.try_borrow_mut() {
+            cleared_slots.extend(slots.drain().map(|(_, sym)| sym));
+            freed += 1;
+        }
+    }
+
+    HEAP.with(|heap| {
+        let mut heap = heap.borrow_mut();
+        // nothing gets allocated while collecting, but keep anything that was anyway
+        live_values.append(&mut heap.values);
+        live_scopes.append(&mut heap.scopes);
+        heap.stats.collections += 1;
+        heap.stats.freed += freed;
+        heap.stats.live = live_values.len() + live_scopes.len();
+        heap.threshold = MIN_THRESHOLD.max(heap.stats.live * 2);
+        heap.since_collection = 0;
+        heap.values = live_values;
+        heap.scopes = live_scopes;
+    });
+    drop(cleared);
+    drop(cleared_slots);
+    drop(values);
+    drop(scopes);
+    freed
+}
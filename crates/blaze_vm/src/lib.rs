@@ -11,44 +11,440 @@
    limitations under the License.
 */
 
-use bzs_shared::{ByteCode, Constants};
-use std::{cell::RefCell, collections::HashMap, mem::MaybeUninit, rc::Rc};
+use bzs_shared::{BigInt, ByteCode, ClassTemplate, Constants, EnumTemplate, Params};
+use std::{
+    cell::{Cell, RefCell},
+    cmp::Ordering,
+    collections::{HashMap, HashSet},
+    convert::TryFrom,
+    rc::Rc,
+};
 
-const STACK_SIZE: usize = 512;
-const SYM_ARR_SIZE: usize = 50;
+pub mod gc;
+mod map;
+
+pub use map::{KeyError, Map, MapKey};
+
+thread_local! {
+    static MAX_DEPTH: Cell<usize> = const { Cell::new(DEFAULT_MAX_DEPTH) };
+}
+
+// How many calls can be nested before a StackOverflowError is thrown
+pub fn set_max_depth(depth: usize) {
+    MAX_DEPTH.with(|max| max.set(depth));
+}
+
+pub fn max_depth() -> usize {
+    MAX_DEPTH.with(|max| max.get())
+}
+
+const DEFAULT_MAX_DEPTH: usize = 1000;
+const BUILTINS: [&str; 17] = [
+    "type",
+    "classof",
+    "properties",
+    "methods",
+    "str",
+    "int",
+    "float",
+    "map",
+    "filter",
+    "sort",
+    "get",
+    "set",
+    "has",
+    "delete",
+    "keys",
+    "values",
+    "gc",
+];
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum Konstants {
     None,
     Null,
     Int(i128),
+    BigInt(BigInt),
     Float(f64),
     String(String),
     Char(char),
     Boolean(bool),
-    Array(Vec<Konstants>),
-    Object(HashMap<usize, Konstants>),
-    Function(Vec<u16>, VM),
+    Array(Vec<K>),
+    Object(HashMap<usize, K>, Option<K>),
+    Map(Map),
+    Function(Params, VM),
+    Class(Class),
+    Enum(Rc<EnumTemplate>),
+    Variant(Rc<EnumTemplate>, usize, Vec<K>),
+    Accessor(Option<K>, Option<K>),
+    Builtin(&'static str),
+    // the start, the end and whether the end is included
+    Range(i128, i128, bool),
+    Iterator(Iter),
+    Error(Exception),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Class {
+    template: Rc<ClassTemplate>,
+    statics: K,
+    parent: Option<K>,
+    vm: VM,
+}
+
+impl Class {
+    pub fn name(&self) -> u16 {
+        self.template.name
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Exception {
+    pub kind: String,
+    pub message: String,
+    pub trace: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Iter {
+    Values(Vec<K>, usize),
+    // the next value, the end and whether the end is still to come, an inclusive range
+    // stops by clearing that so it never steps past the end
+    Range(i128, i128, bool),
+    Object(K),
 }
 
 impl Konstants {
-    pub fn property_edit(&mut self, i: usize, val: Konstants) {
+    pub fn property_edit(&mut self, i: usize, val: K) {
         match self {
-            Self::Object(map) => {
+            Self::Object(map, _) => {
                 map.insert(i, val);
             }
             _ => panic!("property_edit called on unexpected type"),
         }
     }
+
+    // Arrays, plain objects, maps and variants are equal when their contents are, instances,
+    // functions and everything else that has state are only equal to themselves, and
+    // values of different types are never equal except for Ints and Floats
+    pub fn equals(&self, other: &Konstants) -> bool {
+        let mut pending = vec![];
+        if !self.equals_shallow(other, &mut pending) {
+            return false;
+        }
+        // `seen` has the pairs of values already compared or waiting to be, getting back
+        // to one of them through a cycle doesn't find any difference so they count as
+        // equal there. The contents wait in `pending` rather than on the call stack so
+        // values nested any number of levels deep can be compared
+        let mut seen = HashSet::new();
+        while let Some((a, b)) = pending.pop() {
+            if visit(&a, &b, &mut seen) && !a.borrow().equals_shallow(&b.borrow(), &mut pending) {
+                return false;
+            }
+        }
+        true
+    }
+
+    // Compares everything but the contents of the values, the pairs of contents that
+    // also have to be equal are added to `pending`
+    fn equals_shallow(&self, other: &Konstants, pending: &mut Vec<(K, K)>) -> bool {
+        match (self, other) {
+            (Self::None | Self::Null, Self::None | Self::Null) => true,
+            (Self::Int(a), Self::Int(b)) => a == b,
+            (a, b) if a.to_big().is_some() && b.to_big().is_some() => a.to_big() == b.to_big(),
+            (Self::Float(a), Self::Float(b)) => a == b,
+            (Self::Float(f), n) | (n, Self::Float(f)) if n.to_big().is_some() => {
+                num_cmp(n, &Self::Float(*f)) == Some(Ordering::Equal)
+            }
+            (Self::String(a), Self::String(b)) => a == b,
+            (Self::Char(a), Self::Char(b)) => a == b,
+            (Self::Boolean(a), Self::Boolean(b)) => a == b,
+            (Self::Array(a), Self::Array(b)) => all_pending(a, b, pending),
+            (Self::Object(a, None), Self::Object(b, None)) => {
+                a.len() == b.len()
+                    && a.iter().all(|(key, val)| match b.get(key) {
+                        Some(other) => {
+                            pending.push((val.clone(), other.clone()));
+                            true
+                        }
+                        None => false,
+                    })
+            }
+            (Self::Map(a), Self::Map(b)) => {
+                a.len() == b.len()
+                    && a.entries().all(|(key, val)| match b.get(key) {
+                        Some(other) => {
+                            pending.push((val.clone(), other.clone()));
+                            true
+                        }
+                        None => false,
+                    })
+            }
+            (Self::Enum(a), Self::Enum(b)) => Rc::ptr_eq(a, b),
+            (Self::Variant(a, x, payload), Self::Variant(b, y, other_payload)) => {
+                Rc::ptr_eq(a, b) && x == y && all_pending(payload, other_payload, pending)
+            }
+            (Self::Builtin(a), Self::Builtin(b)) => a == b,
+            (Self::Range(a, b, i), Self::Range(x, y, j)) => a == x && b == y && i == j,
+            _ => std::ptr::eq(self, other),
+        }
+    }
+
+    // A total order, so arrays holding values of any type can be sorted: None and Null
+    // come first, then Booleans, numbers (with NaN after every other number), Chars,
+    // Strings and Arrays, which are ordered by their elements. Values of any other type
+    // sort after those and keep their order
+    pub fn compare(&self, other: &Konstants) -> Ordering {
+        let mut arrays = match (self, other) {
+            (Self::Array(a), Self::Array(b)) => vec![(a.clone(), b.clone(), 0)],
+            (a, b) => return a.compare_shallow(b),
+        };
+        // The arrays being compared and how far along they are, kept here rather than on
+        // the call stack so arrays nested any number of levels deep can be compared. A
+        // pair of elements that's already been reached isn't compared again, getting
+        // back to it through a cycle doesn't find any difference
+        let mut seen = HashSet::new();
+        while let Some((a, b, i)) = arrays.last_mut() {
+            let (x, y) = match (a.get(*i), b.get(*i)) {
+                (Some(x), Some(y)) => (x.clone(), y.clone()),
+                _ => {
+                    let ord = a.len().cmp(&b.len());
+                    if ord != Ordering::Equal {
+                        return ord;
+                    }
+                    arrays.pop();
+                    continue;
+                }
+            };
+            *i += 1;
+            if !visit(&x, &y, &mut seen) {
+                continue;
+            }
+            let ord = match (&*x.borrow(), &*y.borrow()) {
+                (Self::Array(a), Self::Array(b)) => {
+                    arrays.push((a.clone(), b.clone(), 0));
+                    continue;
+                }
+                (a, b) => a.compare_shallow(b),
+            };
+            if ord != Ordering::Equal {
+                return ord;
+            }
+        }
+        Ordering::Equal
+    }
+
+    // The order of two values that aren't both arrays
+    fn compare_shallow(&self, other: &Konstants) -> Ordering {
+        fn rank(k: &Konstants) -> u8 {
+            match k {
+                Konstants::None | Konstants::Null => 0,
+                Konstants::Boolean(_) => 1,
+                Konstants::Int(_) | Konstants::BigInt(_) | Konstants::Float(_) => 2,
+                Konstants::Char(_) => 3,
+                Konstants::String(_) => 4,
+                Konstants::Array(_) => 5,
+                _ => 6,
+            }
+        }
+        match (self, other) {
+            (Self::Boolean(a), Self::Boolean(b)) => a.cmp(b),
+            (a, b) if a.to_big().is_some() && b.to_big().is_some() => a.to_big().cmp(&b.to_big()),
+            (a, b) if a.to_float().is_some() && b.to_float().is_some() => num_cmp(a, b)
+                .unwrap_or_else(|| {
+                    let is_nan = |k: &Konstants| matches!(k, Konstants::Float(f) if f.is_nan());
+                    is_nan(a).cmp(&is_nan(b))
+                }),
+            (Self::Char(a), Self::Char(b)) => a.cmp(b),
+            (Self::String(a), Self::String(b)) => a.cmp(b),
+            (a, b) => rank(a).cmp(&rank(b)),
+        }
+    }
+
+    // false, 0, null, empty strings, arrays and maps are falsy, everything else is truthy
+    pub fn is_truthy(&self) -> bool {
+        match self {
+            Self::None | Self::Null => false,
+            Self::Boolean(b) => *b,
+            Self::Int(i) => *i != 0,
+            Self::BigInt(i) => !i.is_zero(),
+            Self::Float(f) => *f != 0.0,
+            Self::String(s) => !s.is_empty(),
+            Self::Array(arr) => !arr.is_empty(),
+            Self::Map(map) => !map.is_empty(),
+            _ => true,
+        }
+    }
+
+    pub fn to_big(&self) -> Option<BigInt> {
+        match self {
+            Self::Int(i) => Some(BigInt::from_i128(*i)),
+            Self::BigInt(i) => Some(i.clone()),
+            _ => None,
+        }
+    }
+
+    pub fn to_float(&self) -> Option<f64> {
+        match self {
+            Self::Int(i) => Some(*i as f64),
+            Self::BigInt(i) => Some(i.to_f64()),
+            Self::Float(f) => Some(*f),
+            _ => None,
+        }
+    }
+
+    pub fn index_edit(&mut self, i: i128, val: K) {
+        match self {
+            Self::Array(arr) => {
+                let slot = arr.get_mut(i as usize).expect("Index out of bound");
+                *slot = val;
+            }
+            _ => panic!("index_edit called on unexpected type"),
+        }
+    }
 }
 
-type K = Rc<RefCell<Konstants>>;
+pub type K = Rc<RefCell<Konstants>>;
+
+// The names the compiler gave ids to, property names only seen at runtime get added to it
+pub type Names = Rc<RefCell<NameTable>>;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct NameTable {
+    names: HashMap<u16, String>,
+    ids: HashMap<String, u16>,
+    // None once every id is taken
+    next: Option<u16>,
+}
+
+impl NameTable {
+    pub fn new(names: HashMap<u16, String>) -> Self {
+        let ids = names.iter().map(|(id, name)| (name.clone(), *id)).collect();
+        let next = names.keys().max().map_or(Some(0), |id| id.checked_add(1));
+        Self { names, ids, next }
+    }
+
+    pub fn get(&self, id: &u16) -> Option<&String> {
+        self.names.get(id)
+    }
+
+    pub fn id(&self, name: &str) -> Option<u16> {
+        self.ids.get(name).cloned()
+    }
+
+    pub fn names(&self) -> &HashMap<u16, String> {
+        &self.names
+    }
+
+    // The id of `name`, giving it the next free one if it has none yet
+    fn intern(&mut self, name: &str) -> Option<u16> {
+        if let Some(id) = self.id(name) {
+            return Some(id);
+        }
+        let id = self.next?;
+        self.next = id.checked_add(1);
+        self.names.insert(id, name.to_string());
+        self.ids.insert(name.to_string(), id);
+        Some(id)
+    }
+}
 
 fn make_k(k: Konstants) -> K {
-    Rc::new(RefCell::new(k))
+    let k = Rc::new(RefCell::new(k));
+    gc::track_value(&k);
+    k
+}
+
+// Pairs of values compared, by address
+type Seen = HashSet<(usize, usize)>;
+
+// Whether `a` and `b` still have to be compared, they don't when they're the same value
+// or when they've already been reached
+fn visit(a: &K, b: &K, seen: &mut Seen) -> bool {
+    !Rc::ptr_eq(a, b) && seen.insert((Rc::as_ptr(a) as usize, Rc::as_ptr(b) as usize))
+}
+
+fn all_pending(a: &[K], b: &[K], pending: &mut Vec<(K, K)>) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    pending.extend(a.iter().cloned().zip(b.iter().cloned()));
+    true
+}
+
+// An Int or BigInt used with a Float becomes a Float too, so that the arms matching
+// two Floats handle mixed arithmetic
+fn promote((rhs, lhs): (Konstants, Konstants)) -> (Konstants, Konstants) {
+    match (&rhs, &lhs) {
+        (Konstants::Float(_), n) | (n, Konstants::Float(_)) if n.to_big().is_some() => (
+            Konstants::Float(rhs.to_float().unwrap()),
+            Konstants::Float(lhs.to_float().unwrap()),
+        ),
+        _ => (rhs, lhs),
+    }
+}
+
+// Compares two numbers exactly, None when either is NaN
+fn num_cmp(a: &Konstants, b: &Konstants) -> Option<Ordering> {
+    match (a, b) {
+        (Konstants::Float(x), Konstants::Float(y)) => x.partial_cmp(y),
+        (Konstants::Float(f), n) => int_float_cmp(&n.to_big()?, *f).map(Ordering::reverse),
+        (n, Konstants::Float(f)) => int_float_cmp(&n.to_big()?, *f),
+        _ => Some(a.to_big()?.cmp(&b.to_big()?)),
+    }
+}
+
+// Converting a large integer to a Float rounds it, or even makes it infinite, so the
+// Float is turned into an integer and a fraction instead
+fn int_float_cmp(i: &BigInt, f: f64) -> Option<Ordering> {
+    if f.is_nan() {
+        return None;
+    }
+    if f.is_infinite() {
+        return Some(if f > 0.0 {
+            Ordering::Less
+        } else {
+            Ordering::Greater
+        });
+    }
+    // every integer up to 2^53 is exact as a Float
+    if let Some(small) = i.to_i128().filter(|s| s.unsigned_abs() <= 1 << 53) {
+        return (small as f64).partial_cmp(&f);
+    }
+    let whole = BigInt::parse(&format!("{:.0}", f.trunc())).unwrap();
+    Some(
+        i.cmp(&whole)
+            .then_with(|| 0.0.partial_cmp(&f.fract()).unwrap()),
+    )
 }
 
 type Symbol = Option<(K, bool)>;
+// Variables by id, every module linked into the program shares the ids so there's no
+// telling how large they get
+type ScopeSlots = RefCell<HashMap<usize, (K, bool)>>;
+type Scope = Rc<ScopeSlots>;
+
+fn make_scope() -> Scope {
+    let scope = Rc::new(RefCell::new(HashMap::new()));
+    gc::track_scope(&scope);
+    scope
+}
+
+// A call in the call chain, every call links to its caller instead of copying the chain,
+// it's only turned into a trace when an error is made
+#[derive(Debug, PartialEq)]
+struct Frame {
+    name: String,
+    depth: usize,
+    caller: Option<Rc<Frame>>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Handler {
+    ip: usize,
+    stack_len: usize,
+    scopes: usize,
+}
 
 pub fn convert_to_usize(int1: u8, int2: u8) -> usize {
     ((int1 as usize) << 8) | int2 as usize
@@ -57,42 +453,61 @@ pub fn convert_to_usize(int1: u8, int2: u8) -> usize {
 #[derive(Debug, Clone, PartialEq)]
 pub struct VM {
     bytecode: ByteCode,
-    stack: [K; STACK_SIZE],
-    stack_ptr: usize,
-    symbols: Vec<[Symbol; SYM_ARR_SIZE]>,
+    stack: Vec<K>,
+    symbols: Vec<Scope>,
+    names: Names,
+    name: Option<u16>,
+    frames: Option<Rc<Frame>>,
+    handlers: Vec<Handler>,
     pub return_val: Rc<RefCell<Konstants>>,
 }
 
 impl VM {
-    pub fn new(bytecode: ByteCode, symbols: Option<Vec<[Symbol; SYM_ARR_SIZE]>>) -> Self {
+    pub fn new(bytecode: ByteCode, symbols: Option<Vec<Scope>>, names: Names) -> Self {
         Self {
             bytecode,
-            stack: unsafe {
-                let mut data: [MaybeUninit<K>; STACK_SIZE] = MaybeUninit::uninit().assume_init();
-
-                for elem in &mut data[..] {
-                    *elem = MaybeUninit::new(make_k(Konstants::None));
-                }
-
-                std::mem::transmute::<_, [K; STACK_SIZE]>(data)
-            },
-            stack_ptr: 0,
+            stack: vec![],
             symbols: if symbols.is_none() {
-                const S: Symbol = None;
-                vec![[S; SYM_ARR_SIZE]]
+                vec![make_scope()]
             } else {
                 symbols.unwrap()
             },
+            names,
+            name: None,
+            frames: None,
+            handlers: vec![],
             return_val: make_k(Konstants::None),
         }
     }
 
-    pub fn run(&mut self) {
+    pub fn run(&mut self) -> Result<(), K> {
         let mut ip = 0;
+        loop {
+            match self.execute(ip) {
+                Ok(()) => return Ok(()),
+                Err(err) => {
+                    let handler = match self.handlers.pop() {
+                        Some(handler) => handler,
+                        None => return Err(err),
+                    };
+                    self.symbols.truncate(handler.scopes);
+                    self.stack.truncate(handler.stack_len);
+                    self.push(err);
+                    ip = handler.ip;
+                }
+            }
+        }
+    }
+
+    fn execute(&mut self, mut ip: usize) -> Result<(), K> {
         while ip < self.bytecode.instructions.len() {
             let address = ip;
             ip += 1;
 
+            if self.overload(self.bytecode.instructions[address])? {
+                continue;
+            }
+
             match self.bytecode.instructions[address] {
                 0x01 => {
                     let idx = convert_to_usize(
@@ -104,79 +519,108 @@ impl VM {
                     let konstant = match k {
                         Constants::RawArray(e) => {
                             let mut arr = vec![];
-                            let vm = VM::new(
+                            let mut vm = VM::new(
                                 ByteCode {
                                     instructions: vec![],
                                     constants: vec![],
                                 },
                                 Some(self.symbols.clone()),
+                                self.names.clone(),
                             );
+                            vm.frames = self.frames.clone();
                             for i in &e {
                                 let mut v_cl = vm.clone();
                                 v_cl.bytecode = i.clone();
-                                v_cl.run();
-                                self.symbols = v_cl.symbols.clone();
-                                arr.push(v_cl.stack[0].borrow().clone());
+                                v_cl.run()?;
+                                arr.push(v_cl.result());
                             }
                             Konstants::Array(arr)
                         }
                         Constants::RawObject(map) => {
                             let mut props = HashMap::new();
-                            let vm = VM::new(
+                            let mut vm = VM::new(
                                 ByteCode {
                                     instructions: vec![],
                                     constants: vec![],
                                 },
                                 Some(self.symbols.clone()),
+                                self.names.clone(),
                             );
+                            vm.frames = self.frames.clone();
                             for (k, v) in &map {
                                 let mut v_clone = vm.clone();
                                 v_clone.bytecode = v.clone();
-                                v_clone.run();
-                                self.symbols = v_clone.symbols.clone();
-                                props.insert(k.clone(), v_clone.stack[0].borrow().clone());
+                                v_clone.run()?;
+                                props.insert(*k, v_clone.result());
+                            }
+                            Konstants::Object(props, None)
+                        }
+                        Constants::Module(imports, exports, body) => {
+                            // Modules only see the modules they import, never the importer
+                            let scope = make_scope();
+                            for id in imports {
+                                if let Some(sym) = self.get_symbol(id as usize) {
+                                    scope.borrow_mut().insert(id as usize, sym);
+                                }
                             }
-                            Konstants::Object(props)
+                            let mut module_vm =
+                                VM::new(body, Some(vec![scope.clone()]), self.names.clone());
+                            module_vm.frames = self.frames.clone();
+                            module_vm.run()?;
+                            let mut props = HashMap::new();
+                            for id in exports {
+                                if let Some((val, _)) = scope.borrow().get(&(id as usize)).cloned()
+                                {
+                                    props.insert(id as usize, val);
+                                }
+                            }
+                            Konstants::Object(props, None)
                         }
-                        Constants::Function(args, body) => {
-                            let fun_vm = VM::new(body, Some(self.symbols.clone()));
+                        Constants::Function(name, args, body) => {
+                            let mut fun_vm =
+                                VM::new(body, Some(self.symbols.clone()), self.names.clone());
+                            fun_vm.name = name;
                             Konstants::Function(args, fun_vm)
                         }
-                        Constants::RawClass(constr, klass) => {
-                            let mut vm = VM::new(
+                        Constants::Enum(template) => Konstants::Enum(Rc::new(template)),
+                        Constants::RawClass(template) => {
+                            let vm = VM::new(
                                 ByteCode {
                                     constants: vec![],
                                     instructions: vec![],
                                 },
                                 Some(self.symbols.clone()),
+                                self.names.clone(),
                             );
-                            let mut args = vec![];
-                            match constr.clone() {
-                                Some((a, b)) => {
-                                    vm.bytecode = b;
-                                    args.extend(a);
-                                }
-                                None => (),
-                            }
 
-                            let soul = make_k(Konstants::Object(HashMap::new()));
-                            for (k, v) in &klass {
-                                let mut v_clone = vm.clone();
+                            let statics = make_k(Konstants::Object(HashMap::new(), None));
+                            let mut static_vm = vm.clone();
+                            static_vm.symbols.push(make_scope());
+                            static_vm
+                                .symbols
+                                .last()
+                                .unwrap()
+                                .borrow_mut()
+                                .insert(0, (statics.clone(), false));
+                            static_vm.frames = self.frames.clone();
+                            for (k, v) in &template.statics {
+                                let mut v_clone = static_vm.clone();
                                 v_clone.bytecode = v.clone();
-                                v_clone.symbols.last_mut().unwrap()[0] =
-                                    Some((soul.clone(), false));
-                                v_clone.run();
-                                self.symbols = v_clone.symbols.clone();
-                                soul.borrow_mut()
-                                    .property_edit(k.clone(), v_clone.stack[0].borrow().clone());
+                                v_clone.run()?;
+                                statics.borrow_mut().property_edit(*k, v_clone.result());
                             }
-                            vm.symbols.last_mut().unwrap()[0] = Some((soul.clone(), false));
-                            vm.return_val = soul.clone();
-                            Konstants::Function(args, vm)
+
+                            Konstants::Class(Class {
+                                template: Rc::new(template),
+                                statics,
+                                parent: None,
+                                vm,
+                            })
                         }
                         Constants::None => Konstants::None,
                         Constants::Null => Konstants::Null,
                         Constants::Int(x) => Konstants::Int(x),
+                        Constants::BigInt(x) => Konstants::BigInt(x),
                         Constants::Float(x) => Konstants::Float(x),
                         Constants::String(x) => Konstants::String(x),
                         Constants::Char(x) => Konstants::Char(x),
@@ -185,61 +629,121 @@ impl VM {
                     self.push(make_k(konstant));
                 }
                 0x02 => {
-                    self.pop();
+                    self.pop()?;
                 }
-                0x03 => match (self.pop().borrow().clone(), self.pop().borrow().clone()) {
-                    (Konstants::Int(rhs), Konstants::Int(lhs)) => {
-                        self.push(make_k(Konstants::Int(lhs + rhs)))
-                    }
+                0x03 => match promote((self.pop()?.borrow().clone(), self.pop()?.borrow().clone()))
+                {
+                    (Konstants::Int(rhs), Konstants::Int(lhs)) => match lhs.checked_add(rhs) {
+                        Some(val) => self.push(make_k(Konstants::Int(val))),
+                        None => self.big_arith(
+                            self.bytecode.instructions[address],
+                            &Konstants::Int(lhs),
+                            &Konstants::Int(rhs),
+                        )?,
+                    },
                     (Konstants::Float(rhs), Konstants::Float(lhs)) => {
                         self.push(make_k(Konstants::Float(lhs + rhs)))
                     }
                     (Konstants::String(rhs), Konstants::String(lhs)) => {
                         self.push(make_k(Konstants::String(lhs + &rhs)))
                     }
-                    _ => panic!("Unknown types to OpAdd"),
-                },
-                0x04 => match (self.pop().borrow().clone(), self.pop().borrow().clone()) {
-                    (Konstants::Int(rhs), Konstants::Int(lhs)) => {
-                        self.push(make_k(Konstants::Int(lhs - rhs)))
+                    (rhs, lhs) if lhs.to_big().is_some() && rhs.to_big().is_some() => {
+                        self.big_arith(self.bytecode.instructions[address], &lhs, &rhs)?
                     }
+                    _ => return Err(self.error("TypeError", "Unknown types to OpAdd")),
+                },
+                0x04 => match promote((self.pop()?.borrow().clone(), self.pop()?.borrow().clone()))
+                {
+                    (Konstants::Int(rhs), Konstants::Int(lhs)) => match lhs.checked_sub(rhs) {
+                        Some(val) => self.push(make_k(Konstants::Int(val))),
+                        None => self.big_arith(
+                            self.bytecode.instructions[address],
+                            &Konstants::Int(lhs),
+                            &Konstants::Int(rhs),
+                        )?,
+                    },
                     (Konstants::Float(rhs), Konstants::Float(lhs)) => {
                         self.push(make_k(Konstants::Float(lhs - rhs)))
                     }
-                    _ => panic!("Unknown types to OpSub"),
-                },
-                0x05 => match (self.pop().borrow().clone(), self.pop().borrow().clone()) {
-                    (Konstants::Int(rhs), Konstants::Int(lhs)) => {
-                        self.push(make_k(Konstants::Int(lhs * rhs)))
+                    (rhs, lhs) if lhs.to_big().is_some() && rhs.to_big().is_some() => {
+                        self.big_arith(self.bytecode.instructions[address], &lhs, &rhs)?
                     }
+                    _ => return Err(self.error("TypeError", "Unknown types to OpSub")),
+                },
+                0x05 => match promote((self.pop()?.borrow().clone(), self.pop()?.borrow().clone()))
+                {
+                    (Konstants::Int(rhs), Konstants::Int(lhs)) => match lhs.checked_mul(rhs) {
+                        Some(val) => self.push(make_k(Konstants::Int(val))),
+                        None => self.big_arith(
+                            self.bytecode.instructions[address],
+                            &Konstants::Int(lhs),
+                            &Konstants::Int(rhs),
+                        )?,
+                    },
                     (Konstants::Float(rhs), Konstants::Float(lhs)) => {
                         self.push(make_k(Konstants::Float(lhs * rhs)))
                     }
                     (Konstants::Int(rhs), Konstants::String(lhs)) => {
                         self.push(make_k(Konstants::String(lhs.repeat(rhs as usize))))
                     }
-                    _ => panic!("Unknown types to OpMultiply"),
+                    (rhs, lhs) if lhs.to_big().is_some() && rhs.to_big().is_some() => {
+                        self.big_arith(self.bytecode.instructions[address], &lhs, &rhs)?
+                    }
+                    _ => return Err(self.error("TypeError", "Unknown types to OpMultiply")),
                 },
-                0x06 => match (self.pop().borrow().clone(), self.pop().borrow().clone()) {
+                0x06 => match promote((self.pop()?.borrow().clone(), self.pop()?.borrow().clone()))
+                {
                     (Konstants::Int(rhs), Konstants::Int(lhs)) => {
-                        self.push(make_k(Konstants::Int(lhs / rhs)))
+                        if rhs == 0 {
+                            return Err(self.error("ArithmeticError", "Division by zero"));
+                        }
+                        match lhs.checked_div(rhs) {
+                            Some(val) => self.push(make_k(Konstants::Int(val))),
+                            None => self.big_arith(
+                                self.bytecode.instructions[address],
+                                &Konstants::Int(lhs),
+                                &Konstants::Int(rhs),
+                            )?,
+                        }
                     }
                     (Konstants::Float(rhs), Konstants::Float(lhs)) => {
                         self.push(make_k(Konstants::Float(lhs / rhs)))
                     }
-                    (Konstants::Int(rhs), Konstants::String(lhs)) => self.push(make_k(
-                        Konstants::String((lhs.as_bytes()[rhs as usize] as char).to_string()),
-                    )),
-                    _ => panic!("Unknown types to OpDivide"),
+                    (Konstants::Int(rhs), Konstants::String(lhs)) => {
+                        match lhs.as_bytes().get(rhs as usize) {
+                            Some(c) if rhs >= 0 => {
+                                self.push(make_k(Konstants::String((*c as char).to_string())))
+                            }
+                            _ => return Err(self.error("IndexError", "Index out of bound")),
+                        }
+                    }
+                    (rhs, lhs) if lhs.to_big().is_some() && rhs.to_big().is_some() => {
+                        self.big_arith(self.bytecode.instructions[address], &lhs, &rhs)?
+                    }
+                    _ => return Err(self.error("TypeError", "Unknown types to OpDivide")),
                 },
-                0x07 => match (self.pop().borrow().clone(), self.pop().borrow().clone()) {
+                0x07 => match promote((self.pop()?.borrow().clone(), self.pop()?.borrow().clone()))
+                {
+                    (Konstants::Int(rhs), Konstants::Int(lhs)) if rhs < 0 => {
+                        self.push(make_k(Konstants::Float((lhs as f64).powf(rhs as f64))))
+                    }
                     (Konstants::Int(rhs), Konstants::Int(lhs)) => {
-                        self.push(make_k(Konstants::Int(lhs.pow(rhs as u32))))
+                        match u32::try_from(rhs).ok().and_then(|rhs| lhs.checked_pow(rhs)) {
+                            Some(val) => self.push(make_k(Konstants::Int(val))),
+                            None => self.big_arith(
+                                self.bytecode.instructions[address],
+                                &Konstants::Int(lhs),
+                                &Konstants::Int(rhs),
+                            )?,
+                        }
                     }
                     (Konstants::Float(rhs), Konstants::Float(lhs)) => {
                         self.push(make_k(Konstants::Float(lhs.powf(rhs))))
                     }
-                    _ => panic!("Unknown types to OpPower"),
+                    (rhs, lhs) if lhs.to_big().is_some() && rhs.to_big().is_some() => {
+                        self.big_arith(self.bytecode.instructions[address], &lhs, &rhs)?
+                    }
+                    _ => return Err(self.error("TypeError", "Unknown types to OpPower")),
                 },
                 0x08 => {
                     ip = convert_to_usize(
@@ -247,82 +751,53 @@ impl VM {
                         self.bytecode.instructions[ip + 1],
                     );
                 }
-                0x09 => match self.pop().borrow().clone() {
-                    Konstants::Boolean(b) => {
-                        if !b {
-                            ip = convert_to_usize(
-                                self.bytecode.instructions[ip],
-                                self.bytecode.instructions[ip + 1],
-                            );
-                        } else {
-                            ip += 2;
-                        }
+                0x09 => {
+                    if !self.pop()?.borrow().is_truthy() {
+                        ip = convert_to_usize(
+                            self.bytecode.instructions[ip],
+                            self.bytecode.instructions[ip + 1],
+                        );
+                    } else {
+                        ip += 2;
                     }
-                    _ => panic!("Unknown types to OpJump"),
-                },
-                0x0A => match self.pop().borrow().clone() {
+                }
+                0x0A => match self.pop()?.borrow().clone() {
                     Konstants::Int(num) => self.push(make_k(Konstants::Int(num * 1))),
+                    Konstants::BigInt(num) => self.push(make_k(Konstants::BigInt(num))),
                     Konstants::Float(num) => self.push(make_k(Konstants::Float(num * 1.0))),
-                    _ => panic!("Unknown arg type to OpPlus"),
+                    _ => return Err(self.error("TypeError", "Unknown arg type to OpPlus")),
                 },
-                0x0B => match self.pop().borrow().clone() {
-                    Konstants::Int(num) => self.push(make_k(Konstants::Int(num * -1))),
+                0x0B => match self.pop()?.borrow().clone() {
+                    Konstants::Int(num) => match num.checked_neg() {
+                        Some(val) => self.push(make_k(Konstants::Int(val))),
+                        None => self.push(make_k(Konstants::BigInt(BigInt::from_i128(num).neg()))),
+                    },
+                    Konstants::BigInt(num) => self.push(make_k(Konstants::BigInt(num.neg()))),
                     Konstants::Float(num) => self.push(make_k(Konstants::Float(num * -1.0))),
-                    _ => panic!("Unknown arg type to OpMinus"),
-                },
-                0x0C => match self.pop().borrow().clone() {
-                    Konstants::Boolean(boolean) => self.push(make_k(Konstants::Boolean(!boolean))),
-                    _ => panic!("Unknown arg type to OpNot"),
-                },
-                0x0D => match (self.pop().borrow().clone(), self.pop().borrow().clone()) {
-                    (Konstants::Boolean(rhs), Konstants::Boolean(lhs)) => {
-                        self.push(make_k(Konstants::Boolean(lhs && rhs)))
-                    }
-                    _ => panic!("Unknown types to OpAnd"),
-                },
-                0x0E => match (self.pop().borrow().clone(), self.pop().borrow().clone()) {
-                    (Konstants::Boolean(rhs), Konstants::Boolean(lhs)) => {
-                        self.push(make_k(Konstants::Boolean(lhs || rhs)))
-                    }
-                    _ => panic!("Unknown types to OpOr"),
-                },
-                0x0F => match (self.pop().borrow().clone(), self.pop().borrow().clone()) {
-                    (Konstants::Int(rhs), Konstants::Int(lhs)) => {
-                        self.push(make_k(Konstants::Boolean(lhs == rhs)))
-                    }
-                    (Konstants::Float(rhs), Konstants::Float(lhs)) => {
-                        self.push(make_k(Konstants::Boolean(lhs == rhs)))
-                    }
-                    (Konstants::String(rhs), Konstants::String(lhs)) => {
-                        self.push(make_k(Konstants::Boolean(lhs == rhs)))
-                    }
-                    (Konstants::Char(rhs), Konstants::Char(lhs)) => {
-                        self.push(make_k(Konstants::Boolean(lhs == rhs)))
-                    }
-                    (Konstants::Boolean(rhs), Konstants::Boolean(lhs)) => {
-                        self.push(make_k(Konstants::Boolean(lhs == rhs)))
-                    }
-                    _ => panic!("Unknown types to OpEquals"),
+                    _ => return Err(self.error("TypeError", "Unknown arg type to OpMinus")),
                 },
-                0x1A => match (self.pop().borrow().clone(), self.pop().borrow().clone()) {
-                    (Konstants::Int(rhs), Konstants::Int(lhs)) => {
-                        self.push(make_k(Konstants::Boolean(lhs != rhs)))
-                    }
-                    (Konstants::Float(rhs), Konstants::Float(lhs)) => {
-                        self.push(make_k(Konstants::Boolean(lhs != rhs)))
-                    }
-                    (Konstants::String(rhs), Konstants::String(lhs)) => {
-                        self.push(make_k(Konstants::Boolean(lhs != rhs)))
-                    }
-                    (Konstants::Char(rhs), Konstants::Char(lhs)) => {
-                        self.push(make_k(Konstants::Boolean(lhs != rhs)))
-                    }
-                    (Konstants::Boolean(rhs), Konstants::Boolean(lhs)) => {
-                        self.push(make_k(Konstants::Boolean(lhs != rhs)))
+                0x0C => {
+                    let truthy = self.pop()?.borrow().is_truthy();
+                    self.push(make_k(Konstants::Boolean(!truthy)))
+                }
+                // `and` and `or` are compiled to jumps now, these only give the same result
+                // for bytecode that still uses them
+                0x0D | 0x0E => {
+                    let (rhs, lhs) = (self.pop()?, self.pop()?);
+                    let truthy = lhs.borrow().is_truthy();
+                    if truthy == (self.bytecode.instructions[address] == 0x0D) {
+                        self.push(rhs)
+                    } else {
+                        self.push(lhs)
                     }
-                    _ => panic!("Unknown types to OpNotEquals"),
-                },
-                0x1B => match (self.pop().borrow().clone(), self.pop().borrow().clone()) {
+                }
+                0x0F | 0x1A => {
+                    let (rhs, lhs) = (self.pop()?, self.pop()?);
+                    let equal = lhs.borrow().equals(&rhs.borrow());
+                    let negate = self.bytecode.instructions[address] == 0x1A;
+                    self.push(make_k(Konstants::Boolean(equal != negate)));
+                }
+                0x1B => match (self.pop()?.borrow().clone(), self.pop()?.borrow().clone()) {
                     (Konstants::Int(rhs), Konstants::Int(lhs)) => {
                         self.push(make_k(Konstants::Boolean(lhs > rhs)))
                     }
@@ -338,9 +813,19 @@ impl VM {
                     (Konstants::Boolean(rhs), Konstants::Boolean(lhs)) => {
                         self.push(make_k(Konstants::Boolean(lhs > rhs)))
                     }
-                    _ => panic!("Unknown types to OpGreaterThan"),
+                    (rhs, lhs) if lhs.to_big().is_some() && rhs.to_big().is_some() => {
+                        self.push(make_k(Konstants::Boolean(lhs.to_big() > rhs.to_big())))
+                    }
+                    (rhs, lhs) if lhs.to_float().is_some() && rhs.to_float().is_some() => {
+                        let ord = num_cmp(&lhs, &rhs);
+                        self.push(make_k(Konstants::Boolean(matches!(
+                            ord,
+                            Some(Ordering::Greater)
+                        ))))
+                    }
+                    _ => return Err(self.error("TypeError", "Unknown types to OpGreaterThan")),
                 },
-                0x1C => match (self.pop().borrow().clone(), self.pop().borrow().clone()) {
+                0x1C => match (self.pop()?.borrow().clone(), self.pop()?.borrow().clone()) {
                     (Konstants::Int(rhs), Konstants::Int(lhs)) => {
                         self.push(make_k(Konstants::Boolean(lhs >= rhs)))
                     }
@@ -356,9 +841,21 @@ impl VM {
                     (Konstants::Boolean(rhs), Konstants::Boolean(lhs)) => {
                         self.push(make_k(Konstants::Boolean(lhs >= rhs)))
                     }
-                    _ => panic!("Unknown types to OpGreaterThanEquals"),
+                    (rhs, lhs) if lhs.to_big().is_some() && rhs.to_big().is_some() => {
+                        self.push(make_k(Konstants::Boolean(lhs.to_big() >= rhs.to_big())))
+                    }
+                    (rhs, lhs) if lhs.to_float().is_some() && rhs.to_float().is_some() => {
+                        let ord = num_cmp(&lhs, &rhs);
+                        self.push(make_k(Konstants::Boolean(matches!(
+                            ord,
+                            Some(Ordering::Greater | Ordering::Equal)
+                        ))))
+                    }
+                    _ => {
+                        return Err(self.error("TypeError", "Unknown types to OpGreaterThanEquals"))
+                    }
                 },
-                0x1D => match (self.pop().borrow().clone(), self.pop().borrow().clone()) {
+                0x1D => match (self.pop()?.borrow().clone(), self.pop()?.borrow().clone()) {
                     (Konstants::Int(rhs), Konstants::Int(lhs)) => {
                         self.push(make_k(Konstants::Boolean(lhs < rhs)))
                     }
@@ -374,9 +871,19 @@ impl VM {
                     (Konstants::Boolean(rhs), Konstants::Boolean(lhs)) => {
                         self.push(make_k(Konstants::Boolean(lhs < rhs)))
                     }
-                    _ => panic!("Unknown types to OpLessThan"),
+                    (rhs, lhs) if lhs.to_big().is_some() && rhs.to_big().is_some() => {
+                        self.push(make_k(Konstants::Boolean(lhs.to_big() < rhs.to_big())))
+                    }
+                    (rhs, lhs) if lhs.to_float().is_some() && rhs.to_float().is_some() => {
+                        let ord = num_cmp(&lhs, &rhs);
+                        self.push(make_k(Konstants::Boolean(matches!(
+                            ord,
+                            Some(Ordering::Less)
+                        ))))
+                    }
+                    _ => return Err(self.error("TypeError", "Unknown types to OpLessThan")),
                 },
-                0x1E => match (self.pop().borrow().clone(), self.pop().borrow().clone()) {
+                0x1E => match (self.pop()?.borrow().clone(), self.pop()?.borrow().clone()) {
                     (Konstants::Int(rhs), Konstants::Int(lhs)) => {
                         self.push(make_k(Konstants::Boolean(lhs <= rhs)))
                     }
@@ -392,9 +899,19 @@ impl VM {
                     (Konstants::Boolean(rhs), Konstants::Boolean(lhs)) => {
                         self.push(make_k(Konstants::Boolean(lhs <= rhs)))
                     }
-                    _ => panic!("Unknown types to OpLessThanEquals"),
+                    (rhs, lhs) if lhs.to_big().is_some() && rhs.to_big().is_some() => {
+                        self.push(make_k(Konstants::Boolean(lhs.to_big() <= rhs.to_big())))
+                    }
+                    (rhs, lhs) if lhs.to_float().is_some() && rhs.to_float().is_some() => {
+                        let ord = num_cmp(&lhs, &rhs);
+                        self.push(make_k(Konstants::Boolean(matches!(
+                            ord,
+                            Some(Ordering::Less | Ordering::Equal)
+                        ))))
+                    }
+                    _ => return Err(self.error("TypeError", "Unknown types to OpLessThanEquals")),
                 },
-                0x1F => match self.pop().borrow().clone() {
+                0x1F => match self.pop()?.borrow().clone() {
                     Konstants::Boolean(b) => {
                         let i = convert_to_usize(
                             self.bytecode.instructions[ip],
@@ -402,12 +919,12 @@ impl VM {
                         );
                         ip += 2;
                         if self.get_symbol(i).is_some() {
-                            panic!("Variable already assigned")
+                            return Err(self.error("ReferenceError", "Variable already assigned"));
                         }
-                        let n = self.pop();
-                        self.symbols.last_mut().unwrap()[i] = Some((n, b));
+                        let n = self.pop()?;
+                        self.symbols.last().unwrap().borrow_mut().insert(i, (n, b));
                     }
-                    _ => panic!("Unknown types to OpVarAssign"),
+                    _ => return Err(self.error("TypeError", "Unknown types to OpVarAssign")),
                 },
                 0x2A => {
                     let i = convert_to_usize(
@@ -415,13 +932,13 @@ impl VM {
                         self.bytecode.instructions[ip + 1],
                     );
                     ip += 2;
-                    self.push(
-                        self.get_symbol(i)
-                            .as_ref()
-                            .expect("Variable not found")
-                            .0
-                            .clone(),
-                    );
+                    match self.get_symbol(i) {
+                        Some((val, _)) => self.push(val),
+                        None => match self.builtin(i) {
+                            Some(builtin) => self.push(builtin),
+                            None => return Err(self.error("ReferenceError", "Variable not found")),
+                        },
+                    }
                 }
                 0x2B => {
                     let i = convert_to_usize(
@@ -430,65 +947,90 @@ impl VM {
                     );
                     ip += 2;
                     if self.get_symbol(i).is_none() {
-                        panic!("No variable found to be reassigned")
+                        return Err(
+                            self.error("ReferenceError", "No variable found to be reassigned")
+                        );
                     }
 
-                    if !self.get_symbol(i).as_ref().unwrap().1 {
-                        panic!("Variable not reassignable")
+                    if !self.get_symbol(i).unwrap().1 {
+                        return Err(self.error("TypeError", "Variable not reassignable"));
                     }
 
-                    let n = self.pop();
-                    self.get_set_symbols(i, Some((n, true)));
+                    let n = self.pop()?;
+                    self.get_set_symbols(i, (n, true));
                 }
                 0x2C => {
-                    const S: Symbol = None;
-                    self.symbols.push([S; SYM_ARR_SIZE]);
+                    self.symbols.push(make_scope());
                 }
                 0x2D => {
                     self.symbols.pop();
                 }
-                0x2E => match self.pop().borrow().clone() {
-                    Konstants::Function(args, mut vm) => {
-                        let eval_args = self.pop().borrow().clone();
-                        if let Konstants::Array(a) = eval_args {
-                            if a.len() != args.len() {
-                                panic!("Expected {} args but found {}", args.len(), a.len());
-                            }
-                            let mut i = 0;
-                            for arg in args {
-                                vm.symbols.last_mut().unwrap()[arg as usize] =
-                                    Some((make_k(a.get(i).unwrap().clone()), true));
-                                i += 1;
-                            }
-                        } else {
-                            panic!("Unknown args")
+                0x2E => {
+                    let func = self.pop()?;
+                    let eval_args = self.pop()?.borrow().clone();
+                    if let Konstants::Array(a) = eval_args {
+                        let result = self.call(&func, a)?;
+                        self.push(result);
+                    } else {
+                        return Err(self.error("TypeError", "Unknown args"));
+                    }
+                }
+                0x2F => {
+                    let idx = self.pop()?;
+                    let arr = self.pop()?;
+                    if matches!(*arr.borrow(), Konstants::Map(_)) {
+                        let key = self.map_key(&idx)?;
+                        let val = match &*arr.borrow() {
+                            Konstants::Map(map) => map.get(&key).cloned(),
+                            _ => None,
+                        };
+                        match val {
+                            Some(val) => self.push(val),
+                            None => return Err(self.error("KeyError", "Key not found")),
                         }
-                        vm.run();
-                        self.symbols = vm.symbols.clone();
-                        self.push(vm.return_val.clone());
+                        continue;
                     }
-                    _ => panic!("Unknown Types applied to OpCall"),
-                },
-                0x2F => match (self.pop().borrow().clone(), self.pop().borrow().clone()) {
-                    (Konstants::Int(i), Konstants::Array(a)) => self.push(make_k(
-                        a.get(i as usize).expect("Index out of bound").clone(),
-                    )),
-                    _ => panic!("Unknown types applied to OpIndexArray"),
-                },
-                0x3A => match self.pop().borrow().clone() {
-                    Konstants::Object(a) => {
-                        let i = convert_to_usize(
-                            self.bytecode.instructions[ip],
-                            self.bytecode.instructions[ip + 1],
-                        );
-                        ip += 2;
-                        self.push(make_k(a.get(&i).expect("Property not found").clone()));
+                    if let Some(name) = self.property_key(&arr, &idx)? {
+                        // a name nothing was ever stored under can't be a property
+                        let val = match self.name_id(&name) {
+                            Some(id) => self.property(&arr, id)?,
+                            None => return Err(self.error("PropertyError", "Property not found")),
+                        };
+                        self.push(val);
+                        continue;
                     }
-                    _ => panic!("Unknown types applied to OpPropertyAcess"),
-                },
+                    let val = match (&*idx.borrow(), &*arr.borrow()) {
+                        (Konstants::Int(i), Konstants::Array(a)) => match a.get(*i as usize) {
+                            Some(val) if *i >= 0 => val.clone(),
+                            _ => return Err(self.error("IndexError", "Index out of bound")),
+                        },
+                        (Konstants::Int(i), Konstants::Variant(_, _, payload)) => {
+                            match payload.get(*i as usize) {
+                                Some(val) if *i >= 0 => val.clone(),
+                                _ => return Err(self.error("IndexError", "Index out of bound")),
+                            }
+                        }
+                        _ => {
+                            return Err(
+                                self.error("TypeError", "Unknown types applied to OpIndexArray")
+                            )
+                        }
+                    };
+                    self.push(val);
+                }
+                0x3A => {
+                    let obj = self.pop()?;
+                    let i = convert_to_usize(
+                        self.bytecode.instructions[ip],
+                        self.bytecode.instructions[ip + 1],
+                    );
+                    ip += 2;
+                    let val = self.property(&obj, i)?;
+                    self.push(val);
+                }
                 0x3B => {
-                    let val = self.pop();
-                    let obj = self.pop();
+                    let val = self.pop()?;
+                    let obj = self.pop()?;
 
                     let i = convert_to_usize(
                         self.bytecode.instructions[ip],
@@ -496,59 +1038,1366 @@ impl VM {
                     );
                     ip += 2;
 
-                    obj.borrow_mut().property_edit(i, val.borrow().clone());
+                    // an assignment's value is the value assigned
+                    self.property_assign(&obj, i, val.clone())?;
+                    self.push(val);
                 }
                 0x3C => {
                     if self.return_val.borrow().clone() == Konstants::None {
-                        self.return_val = self.pop().clone();
+                        self.return_val = self.pop()?.clone();
                     }
-                    return;
+                    return Ok(());
                 }
-                _ => panic!(
-                    "\nPrevious instruction {}\nCurrent Instruction: {}\nNext Instruction: {}\n",
-                    self.bytecode.instructions[address - 1],
-                    self.bytecode.instructions[address],
-                    self.bytecode.instructions[address + 1]
-                ),
-            }
-        }
-    }
-
-    pub fn push(&mut self, node: K) {
-        self.stack[self.stack_ptr] = node;
-        self.stack_ptr += 1;
-    }
-
-    pub fn pop(&mut self) -> K {
-        let node = self.stack[if self.stack_ptr == 0 {
-            self.stack_ptr
-        } else {
-            self.stack_ptr - 1
-        }]
-        .clone();
-        self.stack_ptr -= if self.stack_ptr == 0 { 0 } else { 1 };
-        node
-    }
-
-    pub fn pop_last(&self) -> K {
-        self.stack[self.stack_ptr].clone()
+                0x3D => match promote((self.pop()?.borrow().clone(), self.pop()?.borrow().clone()))
+                {
+                    (Konstants::Int(rhs), Konstants::Int(lhs)) => {
+                        if rhs == 0 {
+                            return Err(self.error("ArithmeticError", "Modulo by zero"));
+                        }
+                        // the smallest Int modulo -1 overflows, even though the result is 0
+                        let rem = lhs.wrapping_rem(rhs);
+                        let rem = if rem != 0 && (rem < 0) != (rhs < 0) {
+                            rem + rhs
+                        } else {
+                            rem
+                        };
+                        self.push(make_k(Konstants::Int(rem)))
+                    }
+                    (Konstants::Float(rhs), Konstants::Float(lhs)) => {
+                        self.push(make_k(Konstants::Float(lhs - rhs * (lhs / rhs).floor())))
+                    }
+                    (rhs, lhs) if lhs.to_big().is_some() && rhs.to_big().is_some() => {
+                        self.big_arith(self.bytecode.instructions[address], &lhs, &rhs)?
+                    }
+                    _ => return Err(self.error("TypeError", "Unknown types to OpModulo")),
+                },
+                0x3E => match promote((self.pop()?.borrow().clone(), self.pop()?.borrow().clone()))
+                {
+                    (Konstants::Int(rhs), Konstants::Int(lhs)) => {
+                        if rhs == 0 {
+                            return Err(self.error("ArithmeticError", "Division by zero"));
+                        }
+                        match lhs.checked_div(rhs) {
+                            Some(quot) => {
+                                let quot = if lhs % rhs != 0 && (lhs < 0) != (rhs < 0) {
+                                    quot - 1
+                                } else {
+                                    quot
+                                };
+                                self.push(make_k(Konstants::Int(quot)))
+                            }
+                            None => self.big_arith(
+                                self.bytecode.instructions[address],
+                                &Konstants::Int(lhs),
+                                &Konstants::Int(rhs),
+                            )?,
+                        }
+                    }
+                    (Konstants::Float(rhs), Konstants::Float(lhs)) => {
+                        self.push(make_k(Konstants::Float((lhs / rhs).floor())))
+                    }
+                    (rhs, lhs) if lhs.to_big().is_some() && rhs.to_big().is_some() => {
+                        self.big_arith(self.bytecode.instructions[address], &lhs, &rhs)?
+                    }
+                    _ => return Err(self.error("TypeError", "Unknown types to OpFloorDivide")),
+                },
+                0x3F => match (self.pop()?.borrow().clone(), self.pop()?.borrow().clone()) {
+                    (Konstants::Int(rhs), Konstants::Int(lhs)) => {
+                        self.push(make_k(Konstants::Int(lhs & rhs)))
+                    }
+                    (Konstants::Boolean(rhs), Konstants::Boolean(lhs)) => {
+                        self.push(make_k(Konstants::Boolean(lhs & rhs)))
+                    }
+                    (rhs, lhs) if lhs.to_big().is_some() && rhs.to_big().is_some() => {
+                        self.big_arith(self.bytecode.instructions[address], &lhs, &rhs)?
+                    }
+                    _ => return Err(self.error("TypeError", "Unknown types to OpBitAnd")),
+                },
+                0x4A => match (self.pop()?.borrow().clone(), self.pop()?.borrow().clone()) {
+                    (Konstants::Int(rhs), Konstants::Int(lhs)) => {
+                        self.push(make_k(Konstants::Int(lhs | rhs)))
+                    }
+                    (Konstants::Boolean(rhs), Konstants::Boolean(lhs)) => {
+                        self.push(make_k(Konstants::Boolean(lhs | rhs)))
+                    }
+                    (rhs, lhs) if lhs.to_big().is_some() && rhs.to_big().is_some() => {
+                        self.big_arith(self.bytecode.instructions[address], &lhs, &rhs)?
+                    }
+                    _ => return Err(self.error("TypeError", "Unknown types to OpBitOr")),
+                },
+                0x4B => match (self.pop()?.borrow().clone(), self.pop()?.borrow().clone()) {
+                    (Konstants::Int(rhs), Konstants::Int(lhs)) => {
+                        self.push(make_k(Konstants::Int(lhs ^ rhs)))
+                    }
+                    (Konstants::Boolean(rhs), Konstants::Boolean(lhs)) => {
+                        self.push(make_k(Konstants::Boolean(lhs ^ rhs)))
+                    }
+                    (rhs, lhs) if lhs.to_big().is_some() && rhs.to_big().is_some() => {
+                        self.big_arith(self.bytecode.instructions[address], &lhs, &rhs)?
+                    }
+                    _ => return Err(self.error("TypeError", "Unknown types to OpBitXor")),
+                },
+                0x4C => match self.pop()?.borrow().clone() {
+                    Konstants::Int(num) => self.push(make_k(Konstants::Int(!num))),
+                    Konstants::BigInt(num) => self.push(make_k(Konstants::BigInt(num.not()))),
+                    _ => return Err(self.error("TypeError", "Unknown arg type to OpBitNot")),
+                },
+                0x4D => match (self.pop()?.borrow().clone(), self.pop()?.borrow().clone()) {
+                    (Konstants::Int(rhs), Konstants::Int(lhs)) => {
+                        if rhs < 0 {
+                            return Err(self.error("ArithmeticError", "Shift amount out of range"));
+                        }
+                        // shifting back gives something else when bits were shifted out
+                        match (rhs < 128).then(|| lhs << rhs) {
+                            Some(val) if val >> rhs == lhs => {
+                                self.push(make_k(Konstants::Int(val)))
+                            }
+                            _ => self.big_arith(
+                                self.bytecode.instructions[address],
+                                &Konstants::Int(lhs),
+                                &Konstants::Int(rhs),
+                            )?,
+                        }
+                    }
+                    (rhs, lhs) if lhs.to_big().is_some() && rhs.to_big().is_some() => {
+                        self.big_arith(self.bytecode.instructions[address], &lhs, &rhs)?
+                    }
+                    _ => return Err(self.error("TypeError", "Unknown types to OpLeftShift")),
+                },
+                0x4E => match (self.pop()?.borrow().clone(), self.pop()?.borrow().clone()) {
+                    (Konstants::Int(rhs), Konstants::Int(lhs)) => {
+                        if rhs < 0 {
+                            return Err(self.error("ArithmeticError", "Shift amount out of range"));
+                        }
+                        self.push(make_k(Konstants::Int(lhs >> rhs.min(127))))
+                    }
+                    (rhs, lhs) if lhs.to_big().is_some() && rhs.to_big().is_some() => {
+                        self.big_arith(self.bytecode.instructions[address], &lhs, &rhs)?
+                    }
+                    _ => return Err(self.error("TypeError", "Unknown types to OpRightShift")),
+                },
+                0x4F => {
+                    let top = self.peek(0)?;
+                    self.push(top);
+                }
+                0x5A => {
+                    let val = self.pop()?;
+                    let idx = self.pop()?;
+                    let arr = self.pop()?;
+
+                    if matches!(*arr.borrow(), Konstants::Map(_)) {
+                        let key = self.map_key(&idx)?;
+                        if let Konstants::Map(map) = &mut *arr.borrow_mut() {
+                            map.set(key, val.clone());
+                        }
+                        self.push(val);
+                        continue;
+                    }
+                    if let Some(name) = self.property_key(&arr, &idx)? {
+                        let id = self.intern(&name)?;
+                        self.property_assign(&arr, id, val.clone())?;
+                        self.push(val);
+                        continue;
+                    }
+                    let i = match (&*arr.borrow(), &*idx.borrow()) {
+                        (Konstants::Array(a), Konstants::Int(i)) => {
+                            if *i < 0 || *i as usize >= a.len() {
+                                return Err(self.error("IndexError", "Index out of bound"));
+                            }
+                            *i
+                        }
+                        _ => {
+                            return Err(
+                                self.error("TypeError", "Unknown types applied to OpIndexAssign")
+                            )
+                        }
+                    };
+                    arr.borrow_mut().index_edit(i, val.clone());
+                    self.push(val);
+                }
+                0x5B => {
+                    let (lhs, rhs) = (self.peek(1)?, self.peek(0)?);
+                    self.push(lhs);
+                    self.push(rhs);
+                }
+                0x5C => {
+                    let iterable = self.pop()?;
+                    let iter = self.make_iter(&iterable)?;
+                    self.push(make_k(Konstants::Iterator(iter)));
+                }
+                0x5D => {
+                    let iterator = self.pop()?;
+                    let user_iter = match &*iterator.borrow() {
+                        Konstants::Iterator(Iter::Object(obj)) => Some(obj.clone()),
+                        _ => None,
+                    };
+                    let next = match user_iter {
+                        Some(obj) => self.next_from_object(&obj)?,
+                        None => match &mut *iterator.borrow_mut() {
+                            Konstants::Iterator(Iter::Values(values, idx)) => {
+                                *idx += 1;
+                                values.get(*idx - 1).cloned()
+                            }
+                            Konstants::Iterator(Iter::Range(current, end, inclusive)) => {
+                                if *current < *end {
+                                    *current += 1;
+                                    Some(make_k(Konstants::Int(*current - 1)))
+                                } else if *current == *end && *inclusive {
+                                    *inclusive = false;
+                                    Some(make_k(Konstants::Int(*current)))
+                                } else {
+                                    None
+                                }
+                            }
+                            _ => {
+                                return Err(
+                                    self.error("TypeError", "Unknown types applied to OpIterNext")
+                                )
+                            }
+                        },
+                    };
+
+                    match next {
+                        Some(val) => {
+                            ip += 2;
+                            self.push(val);
+                        }
+                        None => {
+                            ip = convert_to_usize(
+                                self.bytecode.instructions[ip],
+                                self.bytecode.instructions[ip + 1],
+                            );
+                        }
+                    }
+                }
+                0x5E => match (self.pop()?.borrow().clone(), self.pop()?.borrow().clone()) {
+                    (Konstants::Int(rhs), Konstants::Int(lhs)) => {
+                        self.push(make_k(Konstants::Range(lhs, rhs, false)))
+                    }
+                    _ => return Err(self.error("TypeError", "Unknown types to OpRange")),
+                },
+                0x5F => match (self.pop()?.borrow().clone(), self.pop()?.borrow().clone()) {
+                    (Konstants::Int(rhs), Konstants::Int(lhs)) => {
+                        self.push(make_k(Konstants::Range(lhs, rhs, true)))
+                    }
+                    _ => return Err(self.error("TypeError", "Unknown types to OpRangeInclusive")),
+                },
+                0x6A => {
+                    let to = convert_to_usize(
+                        self.bytecode.instructions[ip],
+                        self.bytecode.instructions[ip + 1],
+                    );
+                    ip += 2;
+                    self.handlers.push(Handler {
+                        ip: to,
+                        stack_len: self.stack.len(),
+                        scopes: self.symbols.len(),
+                    });
+                }
+                0x6B => {
+                    self.handlers.pop();
+                }
+                0x6C => {
+                    let val = self.pop()?;
+                    return Err(val);
+                }
+                0x6D => {
+                    let parent = self.pop()?;
+                    let class = self.pop()?;
+                    if !matches!(*parent.borrow(), Konstants::Class(_)) {
+                        return Err(self.error("TypeError", "Classes can only extend a class"));
+                    }
+                    if let Konstants::Class(c) = &mut *class.borrow_mut() {
+                        c.parent = Some(parent.clone());
+                    }
+                    self.push(class);
+                }
+                0x6E => {
+                    let class = self.pop()?;
+                    let val = self.pop()?;
+                    if !matches!(*class.borrow(), Konstants::Class(_)) {
+                        return Err(
+                            self.error("TypeError", "Right side of instanceof should be a class")
+                        );
+                    }
+                    let mut current = match &*val.borrow() {
+                        Konstants::Object(_, class) => class.clone(),
+                        _ => None,
+                    };
+                    let mut result = false;
+                    while let Some(c) = current {
+                        if Rc::ptr_eq(&c, &class) {
+                            result = true;
+                            break;
+                        }
+                        current = match &*c.borrow() {
+                            Konstants::Class(c) => c.parent.clone(),
+                            _ => None,
+                        };
+                    }
+                    self.push(make_k(Konstants::Boolean(result)));
+                }
+                0x6F => {
+                    let func = self.pop()?;
+                    let named = self.pop()?.borrow().clone();
+                    let eval_args = self.pop()?.borrow().clone();
+                    if let (Konstants::Array(a), Konstants::Object(named, _)) = (eval_args, named) {
+                        let result = self.call_named(&func, a, named)?;
+                        self.push(result);
+                    } else {
+                        return Err(self.error("TypeError", "Unknown args"));
+                    }
+                }
+                0x7A => {
+                    let (rhs, lhs) = (self.pop()?, self.pop()?);
+                    let equal = lhs.borrow().equals(&rhs.borrow());
+                    self.push(make_k(Konstants::Boolean(equal)));
+                }
+                0x7B | 0x7C => {
+                    let len = convert_to_usize(
+                        self.bytecode.instructions[ip],
+                        self.bytecode.instructions[ip + 1],
+                    );
+                    ip += 2;
+                    let exact = self.bytecode.instructions[address] == 0x7B;
+                    let result = match &*self.pop()?.borrow() {
+                        Konstants::Array(a) if exact => a.len() == len,
+                        Konstants::Array(a) => a.len() >= len,
+                        _ => false,
+                    };
+                    self.push(make_k(Konstants::Boolean(result)));
+                }
+                0x7D => {
+                    let start = convert_to_usize(
+                        self.bytecode.instructions[ip],
+                        self.bytecode.instructions[ip + 1],
+                    );
+                    ip += 2;
+                    let rest = match &*self.pop()?.borrow() {
+                        Konstants::Array(a) => a[start.min(a.len())..].to_vec(),
+                        _ => return Err(self.error("TypeError", "Only arrays can be sliced")),
+                    };
+                    self.push(make_k(Konstants::Array(rest)));
+                }
+                0x7E => {
+                    let i = convert_to_usize(
+                        self.bytecode.instructions[ip],
+                        self.bytecode.instructions[ip + 1],
+                    );
+                    ip += 2;
+                    let result = match &*self.pop()?.borrow() {
+                        Konstants::Object(props, _) => props.contains_key(&i),
+                        _ => false,
+                    };
+                    self.push(make_k(Konstants::Boolean(result)));
+                }
+                0x7F => {
+                    let message = match &*self.pop()?.borrow() {
+                        Konstants::String(message) => message.clone(),
+                        _ => String::new(),
+                    };
+                    return Err(self.error("MatchError", &message));
+                }
+                0x8A => {
+                    let variant = convert_to_usize(
+                        self.bytecode.instructions[ip],
+                        self.bytecode.instructions[ip + 1],
+                    ) as u16;
+                    ip += 2;
+                    let (enum_val, val) = (self.pop()?, self.pop()?);
+                    let result = match (&*val.borrow(), &*enum_val.borrow()) {
+                        (Konstants::Variant(a, idx, payload), Konstants::Enum(b)) => {
+                            let (name, fields) = &a.variants[*idx];
+                            Rc::ptr_eq(a, b) && *name == variant && payload.len() == fields.len()
+                        }
+                        (_, Konstants::Enum(_)) => false,
+                        _ => {
+                            return Err(self.error("TypeError", "Only enums have variant patterns"))
+                        }
+                    };
+                    self.push(make_k(Konstants::Boolean(result)));
+                }
+                // jumps when the value on top is null but leaves it there
+                0x8B => {
+                    let top = self.peek(0)?;
+                    if matches!(*top.borrow(), Konstants::None | Konstants::Null) {
+                        ip = convert_to_usize(
+                            self.bytecode.instructions[ip],
+                            self.bytecode.instructions[ip + 1],
+                        );
+                    } else {
+                        ip += 2;
+                    }
+                }
+                0x8C => {
+                    let len = convert_to_usize(
+                        self.bytecode.instructions[ip],
+                        self.bytecode.instructions[ip + 1],
+                    );
+                    ip += 2;
+                    let start = match self.stack.len().checked_sub(len * 2) {
+                        Some(start) => start,
+                        None => return Err(self.error("InternalError", "Stack underflow")),
+                    };
+                    let entries = self.stack.split_off(start);
+                    let mut map = Map::default();
+                    for pair in entries.chunks(2) {
+                        let key = self.map_key(&pair[0])?;
+                        map.set(key, pair[1].clone());
+                    }
+                    self.push(make_k(Konstants::Map(map)));
+                }
+                0x8D => {
+                    let key = self.pop()?;
+                    let target = self.pop()?;
+                    let deleted = if matches!(*target.borrow(), Konstants::Map(_)) {
+                        let key = self.map_key(&key)?;
+                        match &mut *target.borrow_mut() {
+                            Konstants::Map(map) => map.delete(&key).is_some(),
+                            _ => false,
+                        }
+                    } else {
+                        let id = match &*key.borrow() {
+                            Konstants::String(name) => self.name_id(name),
+                            _ => None,
+                        };
+                        match (&mut *target.borrow_mut(), id) {
+                            (Konstants::Object(props, _), Some(id)) => props.remove(&id).is_some(),
+                            (Konstants::Object(..), None) => false,
+                            _ => {
+                                return Err(self.error(
+                                    "TypeError",
+                                    "Only object properties and map keys can be deleted",
+                                ))
+                            }
+                        }
+                    };
+                    self.push(make_k(Konstants::Boolean(deleted)));
+                }
+                _ => panic!(
+                    "\nPrevious instruction {}\nCurrent Instruction: {}\nNext Instruction: {}\n",
+                    self.bytecode.instructions[address - 1],
+                    self.bytecode.instructions[address],
+                    self.bytecode.instructions[address + 1]
+                ),
+            }
+        }
+        Ok(())
+    }
+
+    // Int arithmetic that doesn't fit an i128 or has a BigInt operand, which gives a BigInt
+    fn big_arith(&mut self, op: u8, lhs: &Konstants, rhs: &Konstants) -> Result<(), K> {
+        let (lhs, rhs) = (lhs.to_big().unwrap(), rhs.to_big().unwrap());
+        let val = match op {
+            0x03 => lhs.add(&rhs),
+            0x04 => lhs.sub(&rhs),
+            0x05 => lhs.mul(&rhs),
+            0x07 if rhs.is_negative() => {
+                self.push(make_k(Konstants::Float(lhs.to_f64().powf(rhs.to_f64()))));
+                return Ok(());
+            }
+            0x07 => match rhs.to_i128().and_then(|exp| u32::try_from(exp).ok()) {
+                Some(exp) => lhs.pow(exp),
+                None => return Err(self.error("ArithmeticError", "Exponent too large")),
+            },
+            0x3F => lhs.bit_and(&rhs),
+            0x4A => lhs.bit_or(&rhs),
+            0x4B => lhs.bit_xor(&rhs),
+            0x4D | 0x4E if rhs.is_negative() => {
+                return Err(self.error("ArithmeticError", "Shift amount out of range"))
+            }
+            0x4D => match rhs.to_i128().and_then(|shift| u32::try_from(shift).ok()) {
+                Some(shift) => lhs.shl(shift),
+                None => return Err(self.error("ArithmeticError", "Shift amount too large")),
+            },
+            // a shift that doesn't fit a u32 shifts out every bit anyway
+            0x4E => lhs.shr(
+                rhs.to_i128()
+                    .map_or(u32::MAX, |s| s.min(u32::MAX as i128) as u32),
+            ),
+            _ => {
+                let res = match op {
+                    0x06 => lhs.div_rem(&rhs),
+                    _ => lhs.div_mod(&rhs),
+                };
+                let (quot, rem) = match res {
+                    Some(res) => res,
+                    None if op == 0x3D => {
+                        return Err(self.error("ArithmeticError", "Modulo by zero"))
+                    }
+                    None => return Err(self.error("ArithmeticError", "Division by zero")),
+                };
+                match op {
+                    0x06 | 0x3E => quot,
+                    _ => rem,
+                }
+            }
+        };
+        self.push(make_k(Konstants::BigInt(val)));
+        Ok(())
+    }
+
+    fn error(&self, kind: &str, message: &str) -> K {
+        make_k(Konstants::Error(Exception {
+            kind: String::from(kind),
+            message: String::from(message),
+            trace: self.trace(),
+        }))
+    }
+
+    fn trace(&self) -> Vec<String> {
+        let mut trace = vec![];
+        let mut frame = self.frames.as_deref();
+        while let Some(f) = frame {
+            trace.push(f.name.clone());
+            frame = f.caller.as_deref();
+        }
+        trace
+    }
+
+    fn depth(&self) -> usize {
+        self.frames.as_ref().map_or(0, |f| f.depth)
+    }
+
+    fn frame(&self, vm: &VM, params: &Params) -> String {
+        let name = match vm.name {
+            Some(id) => self.names.borrow().get(&id).cloned(),
+            None => None,
+        };
+        let mut names = params
+            .names
+            .iter()
+            .map(|p| self.names.borrow().get(p).cloned().unwrap_or_default())
+            .collect::<Vec<String>>();
+        if let Some(rest) = params.rest {
+            names.push(format!(
+                "...{}",
+                self.names.borrow().get(&rest).cloned().unwrap_or_default()
+            ));
+        }
+        format!(
+            "{}({})",
+            name.unwrap_or_else(|| String::from("<anonymous>")),
+            names.join(", ")
+        )
+    }
+
+    fn call(&mut self, func: &K, args: Vec<K>) -> Result<K, K> {
+        self.call_named(func, args, HashMap::new())
+    }
+
+    fn call_named(&mut self, func: &K, args: Vec<K>, named: HashMap<usize, K>) -> Result<K, K> {
+        match func.borrow().clone() {
+            Konstants::Function(params, mut vm) => {
+                let named_ids = named.keys().map(|k| *k as u16).collect::<Vec<u16>>();
+                let names = self.names.clone();
+                let name_of = |id: u16| names.borrow().get(&id).cloned().unwrap_or_default();
+                if let Err(e) = params.check(args.len(), &named_ids, name_of) {
+                    return Err(self.error("ArgumentError", &e));
+                }
+                let scope = make_scope();
+                let mut args = args.into_iter();
+                for (param, val) in params.names.iter().zip(&mut args) {
+                    scope.borrow_mut().insert(*param as usize, (val, true));
+                }
+                if let Some(rest) = params.rest {
+                    let rest_val = make_k(Konstants::Array(args.collect()));
+                    scope.borrow_mut().insert(rest as usize, (rest_val, true));
+                }
+                for (param, val) in named {
+                    scope.borrow_mut().insert(param, (val, true));
+                }
+                vm.symbols.push(scope.clone());
+                if self.depth() >= max_depth() {
+                    return Err(self.error(
+                        "StackOverflowError",
+                        &format!("Maximum call depth of {} exceeded", max_depth()),
+                    ));
+                }
+                vm.frames = Some(Rc::new(Frame {
+                    name: self.frame(&vm, &params),
+                    depth: self.depth() + 1,
+                    caller: self.frames.clone(),
+                }));
+
+                // Defaults are evaluated on every call and can see the parameters before them
+                for (param, default) in params.names.iter().zip(&params.defaults) {
+                    if scope.borrow().contains_key(&(*param as usize)) {
+                        continue;
+                    }
+                    if let Some(default) = default {
+                        let mut default_vm = VM::new(
+                            default.clone(),
+                            Some(vm.symbols.clone()),
+                            self.names.clone(),
+                        );
+                        default_vm.frames = vm.frames.clone();
+                        default_vm.run()?;
+                        let val = default_vm.result();
+                        scope.borrow_mut().insert(*param as usize, (val, true));
+                    }
+                }
+                vm.run()?;
+                Ok(vm.return_val)
+            }
+            Konstants::Builtin(_) if !named.is_empty() => Err(self.error(
+                "ArgumentError",
+                "Builtin functions don't take named arguments",
+            )),
+            Konstants::Builtin(name) => self.call_builtin(name, args),
+            Konstants::Variant(template, idx, payload)
+                if payload.is_empty() && !template.variants[idx].1.is_empty() =>
+            {
+                // Fields are checked like the parameters of a function so they can be named
+                let params = Params {
+                    names: template.variants[idx].1.clone(),
+                    defaults: vec![None; template.variants[idx].1.len()],
+                    rest: None,
+                };
+                let named_ids = named.keys().map(|k| *k as u16).collect::<Vec<u16>>();
+                let names = self.names.clone();
+                let name_of = |id: u16| names.borrow().get(&id).cloned().unwrap_or_default();
+                if let Err(e) = params.check(args.len(), &named_ids, name_of) {
+                    return Err(self.error("ArgumentError", &e));
+                }
+                let mut args = args.into_iter();
+                let payload = params
+                    .names
+                    .iter()
+                    .map(|field| match args.next() {
+                        Some(val) => val,
+                        None => named[&(*field as usize)].clone(),
+                    })
+                    .collect();
+                Ok(make_k(Konstants::Variant(template, idx, payload)))
+            }
+            Konstants::Variant(template, idx, _) => Err(self.error(
+                "TypeError",
+                &format!("{} is not a constructor", self.variant_name(&template, idx)),
+            )),
+            Konstants::Class(_) => {
+                let soul = make_k(Konstants::Object(HashMap::new(), Some(func.clone())));
+                let (constructor, _) = self.init_class(func, &soul)?;
+                match constructor {
+                    Some(constructor) => {
+                        self.call_named(&constructor, args, named)?;
+                    }
+                    None if !args.is_empty() || !named.is_empty() => {
+                        return Err(self.error(
+                            "ArgumentError",
+                            &format!("Expected 0 args but found {}", args.len() + named.len()),
+                        ));
+                    }
+                    None => (),
+                }
+                Ok(soul)
+            }
+            _ => Err(self.error("TypeError", "Unknown Types applied to OpCall")),
+        }
+    }
+
+    // Sets up `soul` with the properties of `class` and its parents, parents first so
+    // overrides win, and returns the constructor to run along with every property
+    // bound so far, which is what `super` exposes to a child class.
+    fn init_class(&mut self, class: &K, soul: &K) -> Result<(Option<K>, HashMap<usize, K>), K> {
+        let class = match &*class.borrow() {
+            Konstants::Class(c) => c.clone(),
+            _ => return Err(self.error("TypeError", "Classes can only extend a class")),
+        };
+        let (parent_constructor, mut props) = match &class.parent {
+            Some(parent) => self.init_class(parent, soul)?,
+            None => (None, HashMap::new()),
+        };
+
+        let scope = make_scope();
+        scope.borrow_mut().insert(0, (soul.clone(), false));
+        if class.parent.is_some() {
+            if let Some(id) = self.name_id("super") {
+                let mut parent = props.clone();
+                if let (Some(constructor_id), Some(constructor)) =
+                    (self.name_id("@constructor"), &parent_constructor)
+                {
+                    parent.insert(constructor_id, constructor.clone());
+                }
+                scope
+                    .borrow_mut()
+                    .insert(id, (make_k(Konstants::Object(parent, None)), false));
+            }
+        }
+
+        let mut vm = class.vm.clone();
+        vm.symbols.push(scope);
+        vm.frames = self.frames.clone();
+        for (k, v) in &class.template.props {
+            let mut v_clone = vm.clone();
+            v_clone.bytecode = v.clone();
+            v_clone.run()?;
+            let val = v_clone.result();
+            soul.borrow_mut().property_edit(*k, val.clone());
+            props.insert(*k, val);
+        }
+
+        for (accessors, is_getter) in [
+            (&class.template.getters, true),
+            (&class.template.setters, false),
+        ] {
+            for (k, v) in accessors {
+                let mut v_clone = vm.clone();
+                v_clone.bytecode = v.clone();
+                v_clone.run()?;
+                let fun = v_clone.result();
+
+                // a getter and a setter for the same name can come from different classes
+                let (mut getter, mut setter) = match props.get(k) {
+                    Some(prop) => match &*prop.borrow() {
+                        Konstants::Accessor(getter, setter) => (getter.clone(), setter.clone()),
+                        _ => (None, None),
+                    },
+                    None => (None, None),
+                };
+                if is_getter {
+                    getter = Some(fun);
+                } else {
+                    setter = Some(fun);
+                }
+                let val = make_k(Konstants::Accessor(getter, setter));
+                soul.borrow_mut().property_edit(*k, val.clone());
+                props.insert(*k, val);
+            }
+        }
+
+        let constructor = match class.template.constructor.clone() {
+            Some((params, body)) => {
+                vm.bytecode = body;
+                vm.name = Some(class.template.name);
+                Some(make_k(Konstants::Function(params, vm)))
+            }
+            None => parent_constructor,
+        };
+        Ok((constructor, props))
+    }
+
+    // Runs the special method of an object operand in place of the instruction,
+    // returning false when the operand doesn't define one
+    fn overload(&mut self, op: u8) -> Result<bool, K> {
+        let (name, arity) = match op {
+            0x03 => ("__add", 2),
+            0x04 => ("__sub", 2),
+            0x05 => ("__mul", 2),
+            0x06 => ("__div", 2),
+            0x07 => ("__pow", 2),
+            0x3D => ("__mod", 2),
+            0x3E => ("__floordiv", 2),
+            0x0B => ("__neg", 1),
+            0x0F => ("__eq", 2),
+            0x1A => ("__ne", 2),
+            0x1B => ("__gt", 2),
+            0x1C => ("__ge", 2),
+            0x1D => ("__lt", 2),
+            0x1E => ("__le", 2),
+            0x2F => ("__index", 2),
+            0x5A => ("__set_index", 3),
+            _ => return Ok(false),
+        };
+        let len = self.stack.len();
+        if len < arity {
+            return Ok(false);
+        }
+        let receiver = self.stack[len - arity].clone();
+        if !matches!(*receiver.borrow(), Konstants::Object(..)) {
+            return Ok(false);
+        }
+        let args = self.stack[len - arity + 1..].to_vec();
+
+        let result = match self.method(&receiver, name) {
+            Some(method) => self.call(&method, args.clone())?,
+            None => {
+                let (lt, eq) = (
+                    self.method(&receiver, "__lt"),
+                    self.method(&receiver, "__eq"),
+                );
+                let test = |vm: &mut VM, method: &Option<K>| -> Result<bool, K> {
+                    let result = vm.call(method.as_ref().unwrap(), args.clone())?;
+                    let b = match *result.borrow() {
+                        Konstants::Boolean(b) => b,
+                        _ => {
+                            return Err(
+                                vm.error("TypeError", "__eq and __lt should return a Boolean")
+                            )
+                        }
+                    };
+                    Ok(b)
+                };
+                let derived = match op {
+                    0x1A if eq.is_some() => !test(self, &eq)?,
+                    0x1B if lt.is_some() && eq.is_some() => !test(self, &lt)? && !test(self, &eq)?,
+                    0x1C if lt.is_some() => !test(self, &lt)?,
+                    0x1E if lt.is_some() && eq.is_some() => test(self, &lt)? || test(self, &eq)?,
+                    _ => return Ok(false),
+                };
+                make_k(Konstants::Boolean(derived))
+            }
+        };
+
+        self.stack.truncate(len - arity);
+        // like any other assignment `__set_index` gives the value assigned
+        self.push(if op == 0x5A { args[1].clone() } else { result });
+        Ok(true)
+    }
+
+    pub fn call_method(&mut self, obj: &K, name: &str, args: Vec<K>) -> Result<Option<K>, K> {
+        match self.method(obj, name) {
+            Some(method) => Ok(Some(self.call(&method, args)?)),
+            None => Ok(None),
+        }
+    }
+
+    fn property(&mut self, obj: &K, i: usize) -> Result<K, K> {
+        let val = match &*obj.borrow() {
+            Konstants::Object(props, _) => props.get(&i).cloned(),
+            Konstants::Class(_) => self.static_property(obj, i),
+            Konstants::Enum(template) => template
+                .variants
+                .iter()
+                .position(|(name, _)| *name as usize == i)
+                .map(|idx| make_k(Konstants::Variant(template.clone(), idx, vec![]))),
+            Konstants::Variant(template, idx, payload) => template.variants[*idx]
+                .1
+                .iter()
+                .position(|field| *field as usize == i)
+                .and_then(|pos| payload.get(pos).cloned()),
+            Konstants::Error(e) => match self.names.borrow().get(&(i as u16)).map(|n| n.as_str()) {
+                Some("kind") => Some(make_k(Konstants::String(e.kind.clone()))),
+                Some("message") => Some(make_k(Konstants::String(e.message.clone()))),
+                Some("trace") => Some(make_k(Konstants::Array(
+                    e.trace
+                        .iter()
+                        .map(|t| make_k(Konstants::String(t.clone())))
+                        .collect(),
+                ))),
+                _ => None,
+            },
+            _ => return Err(self.error("TypeError", "Unknown types applied to OpPropertyAcess")),
+        };
+        let val = match val {
+            Some(val) => val,
+            None => return Err(self.error("PropertyError", "Property not found")),
+        };
+
+        let getter = match &*val.borrow() {
+            Konstants::Accessor(getter, _) => Some(getter.clone()),
+            _ => None,
+        };
+        match getter {
+            Some(Some(getter)) => self.call(&getter, vec![]),
+            Some(None) => Err(self.error("PropertyError", "Property has no getter")),
+            None => Ok(val),
+        }
+    }
+
+    // The name of the property `obj[key]` refers to when `obj` is an object or a class
+    fn property_key(&self, obj: &K, key: &K) -> Result<Option<String>, K> {
+        if !matches!(*obj.borrow(), Konstants::Object(..) | Konstants::Class(_)) {
+            return Ok(None);
+        }
+        match &*key.borrow() {
+            Konstants::String(name) => Ok(Some(name.clone())),
+            _ => Err(self.error("TypeError", "Property names should be Strings")),
+        }
+    }
+
+    fn property_assign(&mut self, obj: &K, i: usize, val: K) -> Result<(), K> {
+        let target = match &*obj.borrow() {
+            Konstants::Object(..) => obj.clone(),
+            Konstants::Class(c) => c.statics.clone(),
+            _ => return Err(self.error("TypeError", "Unknown types applied to OpPropertyAssign")),
+        };
+
+        let setter = match &*target.borrow() {
+            Konstants::Object(props, _) => match props.get(&i) {
+                Some(prop) => match &*prop.borrow() {
+                    Konstants::Accessor(_, setter) => Some(setter.clone()),
+                    _ => None,
+                },
+                None => None,
+            },
+            _ => None,
+        };
+        match setter {
+            Some(Some(setter)) => {
+                self.call(&setter, vec![val])?;
+            }
+            Some(None) => return Err(self.error("PropertyError", "Property has no setter")),
+            None => target.borrow_mut().property_edit(i, val),
+        }
+        Ok(())
+    }
+
+    fn static_property(&self, class: &K, i: usize) -> Option<K> {
+        let mut current = Some(class.clone());
+        while let Some(c) = current {
+            let (found, parent) = match &*c.borrow() {
+                Konstants::Class(c) => (
+                    match &*c.statics.borrow() {
+                        Konstants::Object(props, _) => props.get(&i).cloned(),
+                        _ => None,
+                    },
+                    c.parent.clone(),
+                ),
+                _ => (None, None),
+            };
+            if found.is_some() {
+                return found;
+            }
+            current = parent;
+        }
+        None
+    }
+
+    fn builtin(&self, i: usize) -> Option<K> {
+        let name = self.names.borrow().get(&(i as u16)).cloned()?;
+        BUILTINS
+            .iter()
+            .find(|b| **b == name.as_str())
+            .map(|b| make_k(Konstants::Builtin(b)))
+    }
+
+    fn call_builtin(&mut self, name: &str, args: Vec<K>) -> Result<K, K> {
+        let arity = match name {
+            "map" | "filter" | "get" | "has" | "delete" => 2,
+            "set" => 3,
+            "gc" => 0,
+            _ => 1,
+        };
+        if args.len() != arity {
+            return Err(self.error(
+                "ArgumentError",
+                &format!("Expected {} args but found {}", arity, args.len()),
+            ));
+        }
+        if name == "gc" {
+            return Ok(make_k(Konstants::Int(gc::collect() as i128)));
+        }
+        let arg = &args[0];
+        if let "get" | "set" | "has" | "delete" | "keys" | "values" = name {
+            return self.call_map_builtin(name, &args);
+        }
+        let val = match name {
+            "map" | "filter" => {
+                let arr = match &*arg.borrow() {
+                    Konstants::Array(arr) => arr.clone(),
+                    _ => {
+                        return Err(self.error("TypeError", &format!("{}() expects an array", name)))
+                    }
+                };
+                let mut res = vec![];
+                for el in arr {
+                    let result = self.call(&args[1], vec![el.clone()])?;
+                    if name == "map" {
+                        res.push(result);
+                        continue;
+                    }
+                    if result.borrow().is_truthy() {
+                        res.push(el);
+                    }
+                }
+                Konstants::Array(res)
+            }
+            "type" => Konstants::String(self.type_name(arg)),
+            "sort" => match &*arg.borrow() {
+                Konstants::Array(arr) => {
+                    let mut sorted = arr.clone();
+                    sorted.sort_by(|a, b| a.borrow().compare(&b.borrow()));
+                    Konstants::Array(sorted)
+                }
+                _ => return Err(self.error("TypeError", "sort() expects an array")),
+            },
+            "int" => match &*arg.borrow() {
+                Konstants::Int(_) | Konstants::BigInt(_) => return Ok(arg.clone()),
+                Konstants::Float(f) if f.is_nan() || f.is_infinite() => {
+                    return Err(self.error("ValueError", &format!("Cannot convert {} to Int", f)))
+                }
+                // rounds toward zero, a Float too large for an Int gives a BigInt
+                Konstants::Float(f) if f.abs() < 2f64.powi(127) => {
+                    Konstants::Int(f.trunc() as i128)
+                }
+                Konstants::Float(f) => {
+                    Konstants::BigInt(BigInt::parse(&format!("{:.0}", f.trunc())).unwrap())
+                }
+                Konstants::Boolean(b) => Konstants::Int(*b as i128),
+                Konstants::String(s) => match s.trim().parse::<i128>() {
+                    Ok(i) => Konstants::Int(i),
+                    Err(_) => match BigInt::parse(s.trim()) {
+                        Some(i) => Konstants::BigInt(i),
+                        None => {
+                            return Err(self.error("ValueError", &format!("Invalid Int '{}'", s)))
+                        }
+                    },
+                },
+                _ => {
+                    return Err(self.error(
+                        "TypeError",
+                        &format!("Cannot convert {} to Int", self.type_name(arg)),
+                    ))
+                }
+            },
+            "float" => match &*arg.borrow() {
+                Konstants::Boolean(b) => Konstants::Float(*b as i128 as f64),
+                Konstants::String(s) => match s.trim().parse::<f64>() {
+                    Ok(f) => Konstants::Float(f),
+                    Err(_) => {
+                        return Err(self.error("ValueError", &format!("Invalid Float '{}'", s)))
+                    }
+                },
+                k => match k.to_float() {
+                    Some(f) => Konstants::Float(f),
+                    None => {
+                        return Err(self.error(
+                            "TypeError",
+                            &format!("Cannot convert {} to Float", self.type_name(arg)),
+                        ))
+                    }
+                },
+            },
+            "str" => {
+                if let Some(str) = self.call_method(arg, "__str", vec![])? {
+                    return Ok(str);
+                }
+                let str = match &*arg.borrow() {
+                    Konstants::None => String::from("None"),
+                    Konstants::Null => String::from("Null"),
+                    Konstants::Int(i) => i.to_string(),
+                    Konstants::BigInt(i) => i.to_string(),
+                    Konstants::Float(f) => f.to_string(),
+                    Konstants::String(s) => s.clone(),
+                    Konstants::Char(c) => c.to_string(),
+                    Konstants::Boolean(b) => b.to_string(),
+                    Konstants::Range(start, end, inclusive) => {
+                        format!("{}..{}{}", start, if *inclusive { "=" } else { "" }, end)
+                    }
+                    Konstants::Error(e) => format!("{}: {}", e.kind, e.message),
+                    Konstants::Variant(template, idx, payload) => {
+                        let mut str = self.variant_name(template, *idx);
+                        if !payload.is_empty() {
+                            let mut fields = vec![];
+                            for val in payload.clone() {
+                                match &*self.call_builtin("str", vec![val])?.borrow() {
+                                    Konstants::String(s) => fields.push(s.clone()),
+                                    _ => fields.push(String::new()),
+                                }
+                            }
+                            str.push_str(&format!("({})", fields.join(", ")));
+                        }
+                        str
+                    }
+                    Konstants::Map(map) => {
+                        let mut entries = vec![];
+                        for (key, val) in map.entries().cloned().collect::<Vec<_>>() {
+                            let mut strs = vec![];
+                            for k in [key.to_value(), val] {
+                                match &*self.call_builtin("str", vec![k])?.borrow() {
+                                    Konstants::String(s) => strs.push(s.clone()),
+                                    _ => strs.push(String::new()),
+                                }
+                            }
+                            entries.push(strs.join(": "));
+                        }
+                        format!("#{{{}}}", entries.join(", "))
+                    }
+                    _ => {
+                        return Err(self.error(
+                            "TypeError",
+                            &format!("Cannot convert {} to String", self.type_name(arg)),
+                        ))
+                    }
+                };
+                Konstants::String(str)
+            }
+            "classof" => {
+                return Ok(match &*arg.borrow() {
+                    Konstants::Object(_, Some(class)) => class.clone(),
+                    _ => make_k(Konstants::Null),
+                })
+            }
+            _ => {
+                let props = match &*arg.borrow() {
+                    Konstants::Object(props, _) => props.clone(),
+                    Konstants::Class(c) => match &*c.statics.borrow() {
+                        Konstants::Object(props, _) => props.clone(),
+                        _ => HashMap::new(),
+                    },
+                    _ => {
+                        return Err(self.error(
+                            "TypeError",
+                            &format!("{}() expects an object or a class", name),
+                        ))
+                    }
+                };
+                let mut names = props
+                    .iter()
+                    .filter(|(_, v)| {
+                        matches!(*v.borrow(), Konstants::Function(..)) == (name == "methods")
+                    })
+                    .map(|(k, _)| {
+                        self.names
+                            .borrow()
+                            .get(&(*k as u16))
+                            .cloned()
+                            .unwrap_or_default()
+                    })
+                    .collect::<Vec<String>>();
+                names.sort();
+                Konstants::Array(
+                    names
+                        .into_iter()
+                        .map(|n| make_k(Konstants::String(n)))
+                        .collect(),
+                )
+            }
+        };
+        Ok(make_k(val))
+    }
+
+    fn call_map_builtin(&mut self, name: &str, args: &[K]) -> Result<K, K> {
+        if name == "has" {
+            if let Konstants::Object(props, _) = &*args[0].borrow() {
+                let found = match &*args[1].borrow() {
+                    Konstants::String(key) => self.name_id(key).map(|id| props.contains_key(&id)),
+                    _ => return Err(self.error("TypeError", "Property names should be Strings")),
+                };
+                return Ok(make_k(Konstants::Boolean(found.unwrap_or(false))));
+            }
+        }
+        if !matches!(*args[0].borrow(), Konstants::Map(_)) {
+            let expected = if name == "has" {
+                "a Map or an object"
+            } else {
+                "a Map"
+            };
+            return Err(self.error("TypeError", &format!("{}() expects {}", name, expected)));
+        }
+        let key = match args.get(1) {
+            Some(key) => Some(self.map_key(key)?),
+            None => None,
+        };
+        let mut map = args[0].borrow_mut();
+        let map = match &mut *map {
+            Konstants::Map(map) => map,
+            _ => unreachable!(),
+        };
+        let val = match (name, key) {
+            ("get", Some(key)) => {
+                return Ok(map
+                    .get(&key)
+                    .cloned()
+                    .unwrap_or_else(|| make_k(Konstants::Null)))
+            }
+            ("set", Some(key)) => {
+                map.set(key, args[2].clone());
+                return Ok(args[2].clone());
+            }
+            ("has", Some(key)) => Konstants::Boolean(map.get(&key).is_some()),
+            ("delete", Some(key)) => Konstants::Boolean(map.delete(&key).is_some()),
+            ("keys", _) => Konstants::Array(map.entries().map(|(k, _)| k.to_value()).collect()),
+            _ => Konstants::Array(map.entries().map(|(_, v)| v.clone()).collect()),
+        };
+        Ok(make_k(val))
+    }
+
+    // Map keys are copies, so a key can't change once it's in the map
+    fn map_key(&self, k: &K) -> Result<MapKey, K> {
+        match MapKey::from(&k.borrow()) {
+            Ok(key) => Ok(key),
+            Err(KeyError::Unhashable) => Err(self.error(
+                "TypeError",
+                &format!("{} can't be a Map key", self.type_name(k)),
+            )),
+            Err(KeyError::Cyclic) => Err(self.error(
+                "TypeError",
+                "An array that contains itself can't be a Map key",
+            )),
+        }
+    }
+
+    fn type_name(&self, k: &K) -> String {
+        let name = match &*k.borrow() {
+            Konstants::Object(_, Some(class)) => return self.class_name(class),
+            Konstants::Variant(template, ..) => {
+                return self
+                    .names
+                    .borrow()
+                    .get(&template.name)
+                    .cloned()
+                    .unwrap_or_default()
+            }
+            Konstants::Enum(_) => "Enum",
+            Konstants::None => "None",
+            Konstants::Null => "Null",
+            Konstants::Int(_) => "Int",
+            Konstants::BigInt(_) => "BigInt",
+            Konstants::Float(_) => "Float",
+            Konstants::String(_) => "String",
+            Konstants::Char(_) => "Char",
+            Konstants::Boolean(_) => "Boolean",
+            Konstants::Array(_) => "Array",
+            Konstants::Object(..) => "Object",
+            Konstants::Map(_) => "Map",
+            Konstants::Function(..) | Konstants::Builtin(_) => "Function",
+            Konstants::Class(_) => "Class",
+            Konstants::Range(..) => "Range",
+            Konstants::Iterator(_) => "Iterator",
+            Konstants::Error(_) => "Error",
+            Konstants::Accessor(..) => "Accessor",
+        };
+        String::from(name)
+    }
+
+    fn class_name(&self, class: &K) -> String {
+        match &*class.borrow() {
+            Konstants::Class(c) => self
+                .names
+                .borrow()
+                .get(&c.name())
+                .cloned()
+                .unwrap_or_default(),
+            _ => String::new(),
+        }
+    }
+
+    fn variant_name(&self, template: &EnumTemplate, idx: usize) -> String {
+        let name_of = |id: &u16| self.names.borrow().get(id).cloned().unwrap_or_default();
+        format!(
+            "{}.{}",
+            name_of(&template.name),
+            name_of(&template.variants[idx].0)
+        )
+    }
+
+    fn name_id(&self, name: &str) -> Option<usize> {
+        self.names.borrow().id(name).map(|id| id as usize)
+    }
+
+    // The id of a property name computed at runtime, giving it a new one if the
+    // compiler never saw it
+    fn intern(&self, name: &str) -> Result<usize, K> {
+        let id = self.names.borrow_mut().intern(name);
+        match id {
+            Some(id) => Ok(id as usize),
+            None => Err(self.error("RangeError", "Too many property names")),
+        }
+    }
+
+    fn method(&self, obj: &K, name: &str) -> Option<K> {
+        let id = self.name_id(name)?;
+        match &*obj.borrow() {
+            Konstants::Object(props, _) => props.get(&id).cloned(),
+            _ => None,
+        }
+    }
+
+    fn next_from_object(&mut self, obj: &K) -> Result<Option<K>, K> {
+        let has_next = match self.method(obj, "__has_next") {
+            Some(has_next) => has_next,
+            None => return Err(self.error("PropertyError", "Iterator has no method __has_next")),
+        };
+        let has_next = self.call(&has_next, vec![])?;
+        let has_next = match *has_next.borrow() {
+            Konstants::Boolean(b) => b,
+            _ => return Err(self.error("TypeError", "__has_next should return a Boolean")),
+        };
+        if !has_next {
+            return Ok(None);
+        }
+
+        let next = match self.method(obj, "__next") {
+            Some(next) => next,
+            None => return Err(self.error("PropertyError", "Iterator has no method __next")),
+        };
+        Ok(Some(self.call(&next, vec![])?))
+    }
+
+    fn make_iter(&mut self, iterable: &K) -> Result<Iter, K> {
+        let val = iterable.borrow().clone();
+        let iter = match val {
+            Konstants::Array(arr) => Iter::Values(arr, 0),
+            Konstants::String(s) => {
+                Iter::Values(s.chars().map(|c| make_k(Konstants::Char(c))).collect(), 0)
+            }
+            Konstants::Range(start, end, inclusive) => Iter::Range(start, end, inclusive),
+            Konstants::Iterator(iter) => iter,
+            // like objects, maps are iterated over their keys
+            Konstants::Map(map) => {
+                Iter::Values(map.entries().map(|(k, _)| k.to_value()).collect(), 0)
+            }
+            Konstants::Object(props, _) => {
+                if let Some(iter) = self.method(iterable, "__iter") {
+                    let iterator = self.call(&iter, vec![])?;
+                    return Ok(Iter::Object(iterator));
+                }
+                if self.method(iterable, "__next").is_some() {
+                    return Ok(Iter::Object(iterable.clone()));
+                }
+
+                let mut keys = props.keys().collect::<Vec<&usize>>();
+                keys.sort();
+                Iter::Values(
+                    keys.iter()
+                        .map(|k| {
+                            make_k(Konstants::String(
+                                self.names.borrow().get(&(**k as u16)).unwrap().clone(),
+                            ))
+                        })
+                        .collect(),
+                    0,
+                )
+            }
+            _ => return Err(self.error("TypeError", "Value is not iterable")),
+        };
+        Ok(iter)
+    }
+
+    pub fn push(&mut self, node: K) {
+        self.stack.push(node);
+    }
+
+    // Every instruction finds the values it takes on the stack, so running out of them is
+    // a bug in the compiler rather than in the program
+    pub fn pop(&mut self) -> Result<K, K> {
+        self.stack
+            .pop()
+            .ok_or_else(|| self.error("InternalError", "Stack underflow"))
+    }
+
+    // The value `depth` places below the top of the stack
+    fn peek(&self, depth: usize) -> Result<K, K> {
+        match self.stack.len().checked_sub(depth + 1) {
+            Some(i) => Ok(self.stack[i].clone()),
+            None => Err(self.error("InternalError", "Stack underflow")),
+        }
+    }
+
+    // The value the code ended with, every statement and expression leaves one on the stack
+    // so it's only missing when the code returned early
+    pub fn result(&self) -> K {
+        match self.stack.last() {
+            Some(k) => k.clone(),
+            None => make_k(Konstants::None),
+        }
     }
 
-    pub fn get_symbol(&self, k: usize) -> &Symbol {
-        for idx in (0..self.symbols.len()).rev() {
-            let sym = &self.symbols.get(idx).unwrap()[k];
-            if sym.is_some() {
-                return sym;
+    pub fn get_symbol(&self, k: usize) -> Symbol {
+        for scope in self.symbols.iter().rev() {
+            if let Some(sym) = scope.borrow().get(&k) {
+                return Some(sym.clone());
             }
         }
-        &None
+        None
     }
 
-    pub fn get_set_symbols(&mut self, k: usize, n: Symbol) {
-        for idx in (0..self.symbols.len()).rev() {
-            let sym = &self.symbols.get(idx).unwrap()[k];
-            if sym.is_some() {
-                self.symbols.get_mut(idx).unwrap()[k] = n;
+    pub fn get_set_symbols(&mut self, k: usize, n: (K, bool)) {
+        for scope in self.symbols.iter().rev() {
+            if scope.borrow().contains_key(&k) {
+                scope.borrow_mut().insert(k, n);
                 break;
             }
         }
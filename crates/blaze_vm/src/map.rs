@@ -0,0 +1,144 @@
+/*
+   Copyright 2021 BlazifyOrg
+   Licensed under the Apache License, Version 2.0 (the "License");
+   you may not use this file except in compliance with the License.
+   You may obtain a copy of the License at
+       http://www.apache.org/licenses/LICENSE-2.0
+   Unless required by applicable law or agreed to in writing, software
+   distributed under the License is distributed on an "AS IS" BASIS,
+   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+   See the License for the specific language governing permissions and
+   limitations under the License.
+*/
+
+use crate::{make_k, Konstants, K};
+use bzs_shared::BigInt;
+use std::collections::HashMap;
+
+// A value that can be a key of a Map. Keys are copied into one of these, so changing an
+// array after using it as a key doesn't change the key
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum MapKey {
+    Int(i128),
+    BigInt(BigInt),
+    String(String),
+    Char(char),
+    Boolean(bool),
+    Tuple(Vec<MapKey>),
+}
+
+// Why a value couldn't be turned into a key
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum KeyError {
+    Unhashable,
+    Cyclic,
+}
+
+impl MapKey {
+    // A BigInt that fits an Int is the same key as the Int so that keys are equal
+    // whenever the values are
+    pub fn from(k: &Konstants) -> Result<Self, KeyError> {
+        Self::from_in(k, &mut vec![])
+    }
+
+    // `path` holds the arrays `k` is an element of, an array reached again through its
+    // own elements would be an infinite key
+    fn from_in(k: &Konstants, path: &mut Vec<*const Konstants>) -> Result<Self, KeyError> {
+        Ok(match k {
+            Konstants::Int(i) => Self::Int(*i),
+            Konstants::BigInt(i) => match i.to_i128() {
+                Some(i) => Self::Int(i),
+                None => Self::BigInt(i.clone()),
+            },
+            Konstants::String(s) => Self::String(s.clone()),
+            Konstants::Char(c) => Self::Char(*c),
+            Konstants::Boolean(b) => Self::Boolean(*b),
+            Konstants::Array(arr) => {
+                if path.contains(&(k as *const Konstants)) {
+                    return Err(KeyError::Cyclic);
+                }
+                path.push(k);
+                let keys = arr
+                    .iter()
+                    .map(|el| Self::from_in(&el.borrow(), path))
+                    .collect::<Result<Vec<Self>, KeyError>>();
+                path.pop();
+                Self::Tuple(keys?)
+            }
+            _ => return Err(KeyError::Unhashable),
+        })
+    }
+
+    pub fn to_value(&self) -> K {
+        make_k(match self {
+            Self::Int(i) => Konstants::Int(*i),
+            Self::BigInt(i) => Konstants::BigInt(i.clone()),
+            Self::String(s) => Konstants::String(s.clone()),
+            Self::Char(c) => Konstants::Char(*c),
+            Self::Boolean(b) => Konstants::Boolean(*b),
+            Self::Tuple(keys) => Konstants::Array(keys.iter().map(Self::to_value).collect()),
+        })
+    }
+}
+
+// Entries stay in the order they were first set in. Deleting leaves a hole so that the
+// other entries keep their positions, the holes are squeezed out once they outnumber
+// the entries
+#[derive(Debug, Clone, Default)]
+pub struct Map {
+    index: HashMap<MapKey, usize>,
+    entries: Vec<Option<(MapKey, K)>>,
+}
+
+impl Map {
+    pub fn get(&self, key: &MapKey) -> Option<&K> {
+        self.index
+            .get(key)
+            .and_then(|i| self.entries[*i].as_ref())
+            .map(|(_, val)| val)
+    }
+
+    pub fn set(&mut self, key: MapKey, val: K) {
+        match self.index.get(&key) {
+            Some(i) => self.entries[*i] = Some((key, val)),
+            None => {
+                self.index.insert(key.clone(), self.entries.len());
+                self.entries.push(Some((key, val)));
+            }
+        }
+    }
+
+    pub fn delete(&mut self, key: &MapKey) -> Option<K> {
+        let i = self.index.remove(key)?;
+        let (_, val) = self.entries[i].take()?;
+        if self.entries.len() > 2 * self.index.len() {
+            self.compact();
+        }
+        Some(val)
+    }
+
+    fn compact(&mut self) {
+        self.entries.retain(Option::is_some);
+        for (i, (key, _)) in self.entries.iter().flatten().enumerate() {
+            self.index.insert(key.clone(), i);
+        }
+    }
+
+    pub fn entries(&self) -> impl Iterator<Item = &(MapKey, K)> {
+        self.entries.iter().flatten()
+    }
+
+    pub fn len(&self) -> usize {
+        self.index.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.index.is_empty()
+    }
+}
+
+impl PartialEq for Map {
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len() && self.entries().eq(other.entries())
+    }
+}
@@ -16,16 +16,57 @@
 static GLOBAL: mimalloc::MiMalloc = mimalloc::MiMalloc;
 
 use bincode::{deserialize, serialize};
-use blaze_vm::{Konstants, VM};
-use bzs_shared::ByteCode;
+use blaze_vm::{gc, Konstants, NameTable, K, VM};
+use bzs_shared::{ByteCode, DynType, Error, Node};
 use bzsc_bytecode::ByteCodeGen;
 use bzsc_lexer::Lexer;
 use bzsc_parser::Parser;
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::env::args;
+use std::path::{Path, PathBuf};
 use std::process::exit;
+use std::rc::Rc;
 use std::time::SystemTime;
-use std::{collections::HashMap, env::args};
 
-fn format_print(k: &Konstants, props: HashMap<u16, String>) -> String {
+const BASE_STACK_SIZE: usize = 8 * 1024 * 1024;
+const CALL_STACK_SIZE: usize = 128 * 1024;
+
+// `printing` holds the values being printed that `k` is part of, a value that contains
+// itself is printed as a placeholder the second time it's reached
+fn format_print(
+    k: &K,
+    props: HashMap<u16, String>,
+    vm: &mut VM,
+    printing: &mut HashSet<usize>,
+) -> String {
+    if let Ok(Some(str)) = vm.call_method(k, "__str", vec![]) {
+        return format_print(&str, props, vm, printing);
+    }
+
+    let addr = Rc::as_ptr(k) as usize;
+    let k = k.borrow().clone();
+    let placeholder = match &k {
+        Konstants::Array(_) => "[...]",
+        Konstants::Object(..) => "{...}",
+        Konstants::Map(_) => "#{...}",
+        Konstants::Variant(..) => "...",
+        _ => "",
+    };
+    if !placeholder.is_empty() && !printing.insert(addr) {
+        return String::from(placeholder);
+    }
+    let str = format_value(&k, props, vm, printing);
+    printing.remove(&addr);
+    str
+}
+
+fn format_value(
+    k: &Konstants,
+    props: HashMap<u16, String>,
+    vm: &mut VM,
+    printing: &mut HashSet<usize>,
+) -> String {
     match k {
         Konstants::None => {
             format!("None")
@@ -36,6 +77,9 @@ fn format_print(k: &Konstants, props: HashMap<u16, String>) -> String {
         Konstants::Int(i) => {
             format!("{}", i)
         }
+        Konstants::BigInt(i) => {
+            format!("{}", i)
+        }
         Konstants::Float(i) => {
             format!("{}", i)
         }
@@ -51,18 +95,24 @@ fn format_print(k: &Konstants, props: HashMap<u16, String>) -> String {
         Konstants::Array(x_arr) => {
             let mut res = vec![];
             for x in &x_arr[..] {
-                res.push(format_print(x, props.clone()));
+                res.push(format_print(x, props.clone(), vm, printing));
             }
             res.join(", ")
         }
-        Konstants::Object(x) => {
-            let mut str = String::from("{\n    ");
+        Konstants::Object(x, class) => {
+            let mut str = match class {
+                Some(class) => match &*class.borrow() {
+                    Konstants::Class(c) => format!("{} {{\n    ", props.get(&c.name()).unwrap()),
+                    _ => String::from("{\n    "),
+                },
+                None => String::from("{\n    "),
+            };
             for (a, b) in x {
                 str.push_str(
                     format!(
                         "{}: {},\n",
                         props.get(&(*a as u16)).unwrap(),
-                        format_print(b, props.clone())
+                        format_print(b, props.clone(), vm, printing)
                     )
                     .as_str(),
                 );
@@ -71,20 +121,139 @@ fn format_print(k: &Konstants, props: HashMap<u16, String>) -> String {
             str.push_str("\r}");
             str
         }
+        Konstants::Map(map) => {
+            let mut res = vec![];
+            for (key, val) in map.entries() {
+                res.push(format!(
+                    "{}: {}",
+                    format_print(&key.to_value(), props.clone(), vm, printing),
+                    format_print(val, props.clone(), vm, printing)
+                ));
+            }
+            format!("#{{{}}}", res.join(", "))
+        }
         Konstants::Function(x, _) => {
             let mut str = String::from("Function<(");
             let mut arr = vec![];
-            for a in x {
+            for a in &x.names {
                 arr.push(props.get(a).unwrap().clone());
             }
+            if let Some(rest) = x.rest {
+                arr.push(format!("...{}", props.get(&rest).unwrap()));
+            }
             str.push_str(arr.join(", ").as_str());
             str.push(')');
             str.push('>');
             str
         }
+        Konstants::Class(c) => {
+            format!("Class<{}>", props.get(&c.name()).unwrap())
+        }
+        Konstants::Enum(e) => {
+            format!("Enum<{}>", props.get(&e.name).unwrap())
+        }
+        Konstants::Variant(e, idx, payload) => {
+            let (name, _) = &e.variants[*idx];
+            let mut str = format!(
+                "{}.{}",
+                props.get(&e.name).unwrap(),
+                props.get(name).unwrap()
+            );
+            if !payload.is_empty() {
+                let mut res = vec![];
+                for x in payload {
+                    res.push(format_print(x, props.clone(), vm, printing));
+                }
+                str.push_str(format!("({})", res.join(", ")).as_str());
+            }
+            str
+        }
+        Konstants::Builtin(name) => {
+            format!("Builtin<{}>", name)
+        }
+        Konstants::Accessor(..) => String::from("Accessor"),
+        Konstants::Range(start, end, inclusive) => {
+            format!("{}..{}{}", start, if *inclusive { "=" } else { "" }, end)
+        }
+        Konstants::Iterator(_) => String::from("Iterator"),
+        Konstants::Error(e) => {
+            format!("{}: {}", e.kind, e.message)
+        }
     }
 }
 
+// Finds an import relative to the importing file if it starts with `./` or `../`,
+// otherwise in the directories listed in BLAZE_PATH
+fn resolve(importer: &Path, import: &str, search_path: &[PathBuf]) -> Option<PathBuf> {
+    let candidates = if import.starts_with("./") || import.starts_with("../") {
+        vec![importer.parent()?.join(import)]
+    } else {
+        search_path.iter().map(|dir| dir.join(import)).collect()
+    };
+    candidates.into_iter().find_map(|c| c.canonicalize().ok())
+}
+
+// Parses the module at `path` after every module it imports, so `modules` ends up in
+// the order they have to be loaded, the import paths are replaced with the resolved ones
+fn load_module(
+    path: PathBuf,
+    name: String,
+    search_path: &[PathBuf],
+    loading: &mut Vec<PathBuf>,
+    modules: &mut Vec<(PathBuf, Node)>,
+) -> Result<(), Error> {
+    let cnt = std::fs::read_to_string(&path).expect("could not read script");
+    let name = Box::leak(name.into_boxed_str());
+    let content = Box::leak(cnt.into_boxed_str());
+    let tokens = Lexer::new(name, content).lex()?;
+    let parsed = Parser::new(tokens).parse();
+    if let Some(error) = parsed.error {
+        return Err(error);
+    }
+    let mut node = parsed.node.unwrap();
+
+    loading.push(path.clone());
+    if let Node::Statements { statements } = &mut node {
+        for statement in statements {
+            if let Node::ImportNode { path: token, .. } = statement {
+                let resolved = match resolve(&path, &token.value.into_string(), search_path) {
+                    Some(resolved) => resolved,
+                    None => {
+                        return Err(Error::new(
+                            "Import Error",
+                            token.pos_start,
+                            token.pos_end,
+                            "Module not found",
+                        ))
+                    }
+                };
+                if let Some(idx) = loading.iter().position(|p| *p == resolved) {
+                    let cycle = loading[idx..]
+                        .iter()
+                        .chain([resolved].iter())
+                        .map(|p| p.file_name().unwrap_or_default().to_string_lossy())
+                        .collect::<Vec<_>>()
+                        .join(" -> ");
+                    return Err(Error::new(
+                        "Import Error",
+                        token.pos_start,
+                        token.pos_end,
+                        Box::leak(format!("Circular import: {}", cycle).into_boxed_str()),
+                    ));
+                }
+                if !modules.iter().any(|(p, _)| *p == resolved) {
+                    let name = resolved.to_string_lossy().into_owned();
+                    load_module(resolved.clone(), name, search_path, loading, modules)?;
+                }
+                token.value = DynType::String(resolved.to_string_lossy().into_owned());
+            }
+        }
+    }
+    loading.pop();
+    modules.push((path, node));
+    Ok(())
+}
+
 fn main() {
     let file_name = args().nth(1).expect("no path specified");
     let time = SystemTime::now();
@@ -93,31 +262,44 @@ fn main() {
         println!("----Blazescript compiler----");
         println!("Version: 0.0.1");
         println!("File: {}", file_name);
-        let cnt = std::fs::read_to_string(file_name.clone()).expect("could not read script");
+        let path = Path::new(&file_name)
+            .canonicalize()
+            .expect("could not read script");
+        let search_path = match std::env::var_os("BLAZE_PATH") {
+            Some(paths) => std::env::split_paths(&paths).collect(),
+            None => vec![],
+        };
+        let mut modules = vec![];
+        if let Err(error) = load_module(
+            path,
+            file_name.clone(),
+            &search_path,
+            &mut vec![],
+            &mut modules,
+        ) {
+            error.prettify();
+            exit(1);
+        }
 
-        let name = Box::leak(file_name.to_owned().into_boxed_str());
-        let content = Box::leak(cnt.to_owned().into_boxed_str());
-        let lexed = Lexer::new(name, content).lex();
-        let mut tokens = vec![];
-        match lexed {
-            Ok(lexed) => {
-                tokens.extend(lexed);
-            }
-            Err(error) => {
+        let (_, main) = modules.pop().unwrap();
+        let mut bytecode_gen = ByteCodeGen::new();
+        for (path, module) in modules {
+            if let Err(error) =
+                bytecode_gen.compile_module(path.to_string_lossy().into_owned(), module)
+            {
                 error.prettify();
                 exit(1);
             }
         }
-
-        let parsed = Parser::new(tokens).parse();
-        if parsed.error.is_some() || parsed.node.is_none() {
-            parsed.error.unwrap().prettify();
+        let compiled = bytecode_gen.compile_node(main);
+        for warning in bytecode_gen.warnings() {
+            warning.warn();
+        }
+        if let Err(error) = compiled {
+            error.prettify();
             exit(1);
         }
 
-        let mut bytecode_gen = ByteCodeGen::new();
-        bytecode_gen.compile_node(parsed.node.unwrap());
-
         let mut sym = HashMap::new();
         for (k, v) in &bytecode_gen.variables {
             sym.insert(*v, k.clone());
@@ -145,27 +327,78 @@ fn main() {
         println!("----Blaze Virtual Machine----");
         println!("Version: 0.0.1");
         println!("File: {}", file_name);
-        let btc_raw = std::fs::read(file_name.clone()).expect("could not read executable");
-        let bytecode: (ByteCode, HashMap<u16, String>) =
-            deserialize(&btc_raw[..]).expect("deserialization of executable failed");
-        let mut vm = VM::new(bytecode.0, None);
-        vm.run();
-        println!(
-            "Result: {}",
-            format_print(&vm.pop_last().borrow().clone(), bytecode.1)
-        );
-        match time.elapsed() {
-            Ok(elapsed) => {
+        let max_depth = match std::env::var("BLAZE_MAX_DEPTH") {
+            Ok(depth) => depth.parse().expect("BLAZE_MAX_DEPTH should be a number"),
+            Err(_) => blaze_vm::max_depth(),
+        };
+        // Every call in the script nests the Rust calls running it, so the VM gets a
+        // thread with enough stack for the deepest nesting allowed
+        let vm_thread = std::thread::Builder::new()
+            .stack_size(BASE_STACK_SIZE + max_depth * CALL_STACK_SIZE)
+            .spawn(move || {
+                blaze_vm::set_max_depth(max_depth);
+                let btc_raw = std::fs::read(file_name.clone()).expect("could not read executable");
+                let bytecode: (ByteCode, HashMap<u16, String>) =
+                    deserialize(&btc_raw[..]).expect("deserialization of executable failed");
+                if std::env::var_os("BLAZE_GC_STRESS").is_some() {
+                    gc::set_stress(true);
+                }
+                // names of properties added at runtime end up in here too
+                let names = Rc::new(RefCell::new(NameTable::new(bytecode.1)));
+                let mut vm = VM::new(bytecode.0, None, names.clone());
+                if let Err(err) = vm.run() {
+                    let props = names.borrow().names().clone();
+                    eprintln!(
+                        "Uncaught {}",
+                        format_print(&err, props, &mut vm, &mut HashSet::new())
+                    );
+                    if let Konstants::Error(e) = &*err.borrow() {
+                        // deep recursion would print the same frame a thousand times
+                        let mut i = 0;
+                        while i < e.trace.len() {
+                            let repeats = e.trace[i..]
+                                .iter()
+                                .take_while(|f| **f == e.trace[i])
+                                .count();
+                            eprintln!("    at {}", e.trace[i]);
+                            if repeats > 1 {
+                                eprintln!("    ... {} more times", repeats - 1);
+                            }
+                            i += repeats;
+                        }
+                    }
+                    exit(1);
+                }
+                let result = vm.result();
+                let props = names.borrow().names().clone();
                 println!(
-                    "Time taken for Interpretation Process: {} milliseconds",
-                    elapsed.as_millis()
+                    "Result: {}",
+                    format_print(&result, props, &mut vm, &mut HashSet::new())
                 );
-            }
-            Err(e) => {
-                eprintln!("Error: {:?}", e);
-            }
+                if std::env::var_os("BLAZE_GC_STATS").is_some() {
+                    let stats = gc::stats();
+                    println!(
+                        "Heap: {} allocated, {} freed in {} collections, {} live",
+                        stats.allocated, stats.freed, stats.collections, stats.live
+                    );
+                }
+                match time.elapsed() {
+                    Ok(elapsed) => {
+                        println!(
+                            "Time taken for Interpretation Process: {} milliseconds",
+                            elapsed.as_millis()
+                        );
+                    }
+                    Err(e) => {
+                        eprintln!("Error: {:?}", e);
+                    }
+                }
+                exit(0)
+            })
+            .expect("could not start the VM");
+        if vm_thread.join().is_err() {
+            exit(101);
         }
-        exit(0)
     } else {
         eprintln!("Error: File name should end with .bzs(Script) or .bze(Executable)");
         exit(1);
@@ -0,0 +1,419 @@
+/*
+   Copyright 2021 BlazifyOrg
+   Licensed under the Apache License, Version 2.0 (the "License");
+   you may not use this file except in compliance with the License.
+   You may obtain a copy of the License at
+       http://www.apache.org/licenses/LICENSE-2.0
+   Unless required by applicable law or agreed to in writing, software
+   distributed under the License is distributed on an "AS IS" BASIS,
+   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+   See the License for the specific language governing permissions and
+   limitations under the License.
+*/
+
+use blaze_vm::{gc, Konstants, NameTable, K, VM};
+use bzs_shared::Node;
+use bzsc_bytecode::ByteCodeGen;
+use bzsc_lexer::Lexer;
+use bzsc_parser::Parser;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+// Scalars print the way `println` does, strings are quoted so they can't be mistaken
+// for numbers
+fn show(k: &K) -> String {
+    match &*k.borrow() {
+        Konstants::None => String::from("None"),
+        Konstants::Null => String::from("Null"),
+        Konstants::Int(i) => i.to_string(),
+        Konstants::BigInt(i) => i.to_string(),
+        Konstants::Float(f) => format!("{:?}", f),
+        Konstants::String(s) => format!("{:?}", s),
+        Konstants::Boolean(b) => b.to_string(),
+        Konstants::Array(arr) => {
+            format!("[{}]", arr.iter().map(show).collect::<Vec<_>>().join(", "))
+        }
+        k => panic!("can't show {:?}", k),
+    }
+}
+
+fn parse(name: &'static str, src: String) -> Result<Node, String> {
+    let tokens = Lexer::new(name, Box::leak(src.into_boxed_str()))
+        .lex()
+        .map_err(|e| e.description.to_string())?;
+    let parsed = Parser::new(tokens).parse();
+    match parsed.error {
+        Some(e) => Err(e.description.to_string()),
+        None => Ok(parsed.node.unwrap()),
+    }
+}
+
+// Compiles and runs `src` the way the two halves of the binary do, an uncaught error
+// comes back as its kind and message
+fn eval(src: &'static str) -> Result<String, String> {
+    eval_with(vec![], String::from(src))
+}
+
+// Like `eval` with `modules` linked in first, in order, `import` finds them by name
+fn eval_with(modules: Vec<(&'static str, String)>, src: String) -> Result<String, String> {
+    run(modules, src, false)
+}
+
+// The heap is per thread, so stress mode only applies to the program run here
+fn run(modules: Vec<(&'static str, String)>, src: String, stress: bool) -> Result<String, String> {
+    std::thread::Builder::new()
+        .stack_size(8 * 1024 * 1024)
+        .spawn(move || {
+            gc::set_stress(stress);
+            let mut bytecode_gen = ByteCodeGen::new();
+            for (name, module) in modules {
+                bytecode_gen
+                    .compile_module(String::from(name), parse(name, module)?)
+                    .map_err(|e| e.description.to_string())?;
+            }
+            bytecode_gen
+                .compile_node(parse("test.bzs", src)?)
+                .map_err(|e| e.description.to_string())?;
+            let mut names = HashMap::new();
+            for (k, v) in &bytecode_gen.variables {
+                names.insert(*v, k.clone());
+            }
+            let mut vm = VM::new(
+                bytecode_gen.bytecode,
+                None,
+                Rc::new(RefCell::new(NameTable::new(names))),
+            );
+            match vm.run() {
+                Ok(()) => Ok(show(&vm.result())),
+                Err(err) => match &*err.borrow() {
+                    Konstants::Error(e) => Err(format!("{}: {}", e.kind, e.message)),
+                    k => Err(format!("{:?}", k)),
+                },
+            }
+        })
+        .unwrap()
+        .join()
+        .unwrap()
+}
+
+fn assert_eval(src: &'static str, expected: &str) {
+    assert_eq!(eval(src), Ok(String::from(expected)), "{}", src);
+}
+
+fn assert_error(src: &'static str, expected: &str) {
+    assert_eq!(eval(src), Err(String::from(expected)), "{}", src);
+}
+
+#[test]
+fn int_overflow_promotes_to_big_ints() {
+    assert_eval(
+        "[170141183460469231731687303715884105727 + 1, -170141183460469231731687303715884105728 - 1, -(-170141183460469231731687303715884105728)]",
+        "[170141183460469231731687303715884105728, -170141183460469231731687303715884105729, 170141183460469231731687303715884105728]",
+    );
+    assert_eval(
+        "[2 ^ 130, (2 ^ 130) // (2 ^ 129), 3 ^ -1, 7 / 2, -7 // 2, -7 % 2, 7 % -2]",
+        "[1361129467683753853853498429727072845824, 2, 0.3333333333333333, 3, -4, 1, -1]",
+    );
+}
+
+#[test]
+fn shifts_and_bitwise_ops_promote_to_big_ints() {
+    assert_eval(
+        "[1 << 127, -1 << 127, 1 << 130, 5 << 2, (1 << 130) >> 128, -1 >> 200, 1 >> 200]",
+        "[170141183460469231731687303715884105728, -170141183460469231731687303715884105728, 1361129467683753853853498429727072845824, 20, 4, -1, 0]",
+    );
+    assert_eval(
+        "[(2 ^ 130 + 5) & 7, (2 ^ 130) | 1, (2 ^ 130) ^^ (2 ^ 130), ~(2 ^ 130), -(2 ^ 130) & 12]",
+        "[5, 1361129467683753853853498429727072845825, 0, -1361129467683753853853498429727072845825, 0]",
+    );
+    assert_error("1 << -1", "ArithmeticError: Shift amount out of range");
+}
+
+#[test]
+fn division_by_zero_raises() {
+    assert_error("1 / 0", "ArithmeticError: Division by zero");
+    assert_error("1 // 0", "ArithmeticError: Division by zero");
+    assert_error("1 % 0", "ArithmeticError: Modulo by zero");
+    assert_error("(2 ^ 130) // 0", "ArithmeticError: Division by zero");
+    assert_eval(
+        "try { 1 / 0 } catch (e) { e.message }",
+        "\"Division by zero\"",
+    );
+}
+
+#[test]
+fn ints_and_floats_mix() {
+    assert_eval(
+        "[1 + 2.5, 2.5 - 1, 3 * 0.5, 1 / 4.0, 7.5 % 2, 7 // 2.0, int(-3.9), float(3)]",
+        "[3.5, 1.5, 1.5, 0.25, 1.5, 3.0, -3, 3.0]",
+    );
+    assert_eval(
+        "var x = 1; [x < 0.5, 2 >= 2.0, 1 == 1.0, 1.5 != 1, 2 ^ 200 > 1.5]",
+        "[false, true, true, true, true]",
+    );
+    assert_eval(
+        "[float(10 ^ 40) == float(\"1e40\"), float(2 ^ 200) == 2.0 ^ 200.0, 2 ^ 200 + 0.5]",
+        "[true, true, 1.6069380442589903e60]",
+    );
+}
+
+#[test]
+fn int_float_comparisons_are_exact() {
+    assert_eval(
+        "[2 ^ 53 + 1 == float(2 ^ 53 + 1), 2 ^ 53 + 1 > float(2 ^ 53 + 1), 2 ^ 130 == float(2 ^ 130), 2 ^ 130 + 1 > float(2 ^ 130)]",
+        "[false, true, true, true]",
+    );
+    assert_eval(
+        "var inf = 1.0 / 0.0; [2 ^ 2000 < inf, -(2 ^ 2000) > -inf, 2 ^ 2000 == inf, 170141183460469231731687303715884105727 < inf]",
+        "[true, true, false, true]",
+    );
+    assert_eval(
+        "var nan = float(\"nan\"); [nan == nan, nan != nan, nan < 1, nan >= 1, 1 == nan, 2 ^ 130 < nan]",
+        "[false, true, false, false, false, false]",
+    );
+}
+
+#[test]
+fn structural_equality() {
+    assert_eval(
+        "[[1, [2, \"a\"]] == [1, [2, \"a\"]], [1, 2] == [1, 2, 3], [1] == [1.0], {\"a\": 1, \"b\": [2]} == {\"b\": [2], \"a\": 1}, {\"a\": 1} == {\"a\": 2}]",
+        "[true, false, true, true, false]",
+    );
+    assert_eval(
+        "var f = fun() => 1; var g = fun() => 1; var x = null; [f == f, f == g, x == null, 1 == null, [null] == [null], 1 == \"1\"]",
+        "[true, false, true, false, true, false]",
+    );
+}
+
+#[test]
+fn mixed_types_sort_in_a_total_order() {
+    assert_eval(
+        "sort([3, \"b\", 1.5, null, true, [2], \"a\", 2, [1, 5], false, -1])",
+        "[Null, false, true, -1, 1.5, 2, 3, \"a\", \"b\", [1, 5], [2]]",
+    );
+}
+
+#[test]
+fn equality_on_cycles_terminates() {
+    assert_eval(
+        "var a = [1]; a[0] = a; var b = [1]; b[0] = b; var c = [1, 2]; c[1] = c; var d = [0, 3]; d[1] = d; [a == a, a == b, c == d, sort([d, c])[0][0]]",
+        "[true, true, false, 0]",
+    );
+    assert_eval(
+        "var o = {\"a\": 1}; o.s = o; var p = {\"a\": 1}; p.s = p; var q = {\"a\": 2}; q.s = q; var m = #{}; m[\"me\"] = m; var n = #{}; n[\"me\"] = n; [o == p, o == q, m == n]",
+        "[true, false, true]",
+    );
+}
+
+#[test]
+fn equality_on_deeply_nested_values() {
+    // the arrays are taken apart one level at a time at the end, dropping them whole
+    // would recurse as deep as they're nested
+    assert_eval(
+        "var a = [0]\nvar b = [0]\nfor i in 0..200000 { a = [a]\nb = [b] }\nvar r = [a == b, a == [a], sort([[a], a])[0] == a]\nfor i in 0..200000 { a = a[0]\nb = b[0] }\nr",
+        "[true, false, true]",
+    );
+}
+
+#[test]
+fn truthiness() {
+    assert_eval(
+        "var out = \"\"\nif \"\" { out += \"E\"; } else { out += \"F\"; }\nif [0] { out += \"A\"; }\nvar n = 3\nwhile n { n -= 1; }\n[out, n]",
+        "[\"FA\", 0]",
+    );
+    assert_eval(
+        "[not 0, not [1], not null, not \"\", not [], not 0.0, not \"0\"]",
+        "[true, false, true, true, true, true, false]",
+    );
+}
+
+#[test]
+fn comments_end_at_the_newline() {
+    assert_eval(
+        "var n = 0\nwhile n < 10 {\n    n += 1\n    if n % 2 == 0 { continue } @ a comment\n    if n > 6 { break } @ another one\n}\nn",
+        "7",
+    );
+    assert_eval(
+        "var out = []\nif false { out = [1] } @ c\nelse if false { out = [2] }\n\nelse { out = [3] }\ntry { throw 4 }\ncatch (e) { out = [out[0], e] } @ c\nfinally { out = [out[0], out[1], 5] }\nout",
+        "[3, 4, 5]",
+    );
+}
+
+#[test]
+fn empty_bodies() {
+    assert_eval(
+        "var n = 0\nfor i = 0 to 10 step 0 { }\nfor x in [1, 2] { }\nwhile n < 3 { n += 1\n}\nif n == 3 { } else { n = 9 }\nfun f() => { }\ntry { } catch (e) { } finally { }\nclass C { }\n[n, type(new C())]",
+        "[3, \"C\"]",
+    );
+}
+
+#[test]
+fn readme_control_flow_examples_run() {
+    let readme = include_str!("../../../README.md");
+    for heading in ["- Loops", "- Iterating with for-in", "- Error handling"] {
+        let section = &readme[readme.find(heading).unwrap()..];
+        let example = section.split("```bzs\n").nth(1).unwrap();
+        let example = example.split("```").next().unwrap();
+        // `println` only has to take the value here, and the 0 at the end is because the
+        // last value of an example may be something `show` can't print
+        let src = String::from("var println = (x) => x\n") + example + "0";
+        let result = eval_with(vec![], src);
+        assert!(result.is_ok(), "{}: {:?}", heading, result);
+    }
+}
+
+#[test]
+fn every_statement_has_a_value() {
+    assert_eval(
+        "val blk = match 5 {\n    n if n > 3 => {\n        val d = n * 2\n        d + 1\n    }\n    _ => 0\n}\nvar no_else = if false { 1 }\nvar taken = if true { var k = 2 }\nvar o = {}\nvar a = [0]\nvar m = #{}\n[blk, no_else, taken, o.x = 3, a[0] = 4, m[\"k\"] = 5, a[0] += 1, while false { }]",
+        "[11, Null, 2, 3, 4, 5, 5, Null]",
+    );
+    assert_eval("var n = 0\nfor i in 0..3 { n += i }", "Null");
+    assert_eval("var n = 0\ntry { n = 1 } finally { n = 2 }", "1");
+}
+
+#[test]
+fn and_or_short_circuit() {
+    assert_eval(
+        "var calls = [0]; var hit = fun(v) => { calls[0] += 1; return v; }; [0 or \"default\", \"x\" or hit(1), null and hit(2), 5 and \"five\", false or 0, calls[0]]",
+        "[\"default\", \"x\", Null, \"five\", 0, 0]",
+    );
+}
+
+#[test]
+fn optional_calls_skip_their_arguments() {
+    assert_eval(
+        "var calls = [0]; var g = fun() => { calls[0] += 1; return 5; }; var f = null; var h = fun(x) => x * 2; var o = {\"m\": null}; [f?.(g()), h?.(g()), o.m?.(g(), g()), calls[0]]",
+        "[Null, 10, Null, 1]",
+    );
+}
+
+#[test]
+fn arity_checks_skip_rebound_names() {
+    assert_eval(
+        "if true { fun g(a) => a; g(1) }\nvar g = (a, b) => b\ng(1, 2)",
+        "2",
+    );
+    assert_eval(
+        "if true { fun g(a) => a }\nvar r = 0\nfor g in [(a, b) => b] { r = g(1, 2) }\nr",
+        "2",
+    );
+    assert_eval(
+        "if true { fun g(a) => a }\ntry { throw (a, b) => b } catch (g) { g(1, 2) }",
+        "2",
+    );
+    assert_eval(
+        "if true { fun g(a) => a }\nmatch (a, b) => b { g => g(1, 2) }",
+        "2",
+    );
+    assert_eval(
+        "if true { fun g(a) => a }\nvar [g] = [(a, b) => b]\ng(1, 2)",
+        "2",
+    );
+    assert_error("fun g(a) => a; g(1, 2)", "Expected 1 args but found 2");
+    assert_eval("fun g(a, b = 2) => a + b; g(1)", "3");
+}
+
+#[test]
+fn modules_share_more_names_than_a_scope_used_to_hold() {
+    let names = |prefix: &str, n: usize| {
+        (0..n)
+            .map(|i| format!("var {}{} = {}\n", prefix, i, i))
+            .collect::<String>()
+    };
+    assert_eq!(
+        eval_with(
+            vec![("a.bzs", names("a", 30) + "export var total = a0 + a29")],
+            String::from("import { total } from \"a.bzs\"\n") + &names("m", 25) + "[total, m24]",
+        ),
+        Ok(String::from("[29, 24]"))
+    );
+}
+
+#[test]
+fn computed_property_names() {
+    assert_eval(
+        "var o = {}\nfor i in 0..2000 { o[\"k\" + str(i)] = i }\nvar s = 0\nfor i in 0..2000 { s += o[\"k\" + str(i)] }\n[s, o.k1999, has(o, \"k5\"), has(o, \"nope\")]",
+        "[1999000, 1999, true, false]",
+    );
+    assert_error(
+        "var o = {}\no[\"nope\"]",
+        "PropertyError: Property not found",
+    );
+    assert_eval(
+        "var o = {}\ntry { o[\"nope\"] } catch (e) { 0 }\no[\"nope\"] = 1\no.nope",
+        "1",
+    );
+}
+
+#[test]
+fn ranges_reaching_the_largest_int() {
+    assert_eval(
+        "var M = 170141183460469231731687303715884105727\nvar s = 0\nfor i in (M - 2)..M { s += i - M }\nfor i in (M - 1)..=M { s += (i - M) * 10 }\ns",
+        "-13",
+    );
+    assert_eval(
+        "var n = 0\nfor i in 0..=5 { n += i }\nfor i in 5..=4 { n += 100 }\nfor i in 3..=3 { n += 1000 }\n[n, str(0..=5), str(0..5), 0..=5 == 0..6]",
+        "[1015, \"0..=5\", \"0..5\", false]",
+    );
+}
+
+#[test]
+fn map_keys_and_deletes() {
+    assert_error(
+        "var a = [1]\na[0] = a\nvar m = #{}\nm[a] = 1",
+        "TypeError: An array that contains itself can't be a Map key",
+    );
+    assert_eval(
+        "var b = [1]\nvar m = #{}\nm[[b, [b]]] = 1\nm[[[1], [[1]]]]",
+        "1",
+    );
+    assert_eval(
+        "var m = #{}\nfor i in 0..20000 { m[i] = i }\nfor i in 0..19995 { delete m[i] }\nm[3] = 3\ndelete m[19996]\nm[19999] = -1\n[keys(m), values(m)]",
+        "[[19995, 19997, 19998, 19999, 3], [19995, 19997, 19998, -1, 3]]",
+    );
+}
+
+#[test]
+fn gc_frees_cycles() {
+    assert_eval(
+        "fun pair() => {\n    var a = {\"next\": 0}\n    var b = {\"next\": a}\n    a.next = b\n}\npair()\n[gc(), gc()]",
+        "[2, 0]",
+    );
+    assert_eval(
+        "class Node {\n    var value = 0;\n    var next = 0;\n    fun(v) => {\n        soul.value = v\n    }\n    fun link(other) => {\n        soul.next = other\n        other.next = soul\n    }\n}\nfor i in 0..50 { new Node(i).link(new Node(i + 1)) }\nval kept = new Node(7)\nkept.link(new Node(8))\n[gc() > 100, gc(), kept.next.next.value, kept.next.value]",
+        "[true, 0, 7, 8]",
+    );
+}
+
+#[test]
+fn programs_run_the_same_under_gc_stress() {
+    let src = "class Counter {\n    var n = 0;\n    fun() => {}\n    fun add(k) => {\n        soul.n += k\n        return soul\n    }\n}\nfun adder(k) => (x) => x + k\nval c = new Counter()\nval m = #{}\nfor i in 0..30 {\n    c.add(adder(i)(1))\n    set(m, [i], {\"self\": c})\n}\nval xs = map([3, 1, 2], (x) => [x])\nvar caught = 0\ntry { xs[5] } catch (e) { caught = 1 }\n[c.n, get(m, [29]).self.n, sort(xs), caught, str(1.5)]";
+    let expected = eval_with(vec![], String::from(src));
+    assert_eq!(
+        expected,
+        Ok(String::from("[465, 465, [[1], [2], [3]], 1, \"1.5\"]"))
+    );
+    assert_eq!(run(vec![], String::from(src), true), expected);
+}
+
+#[test]
+fn modules_import_each_other() {
+    assert_eq!(
+        eval_with(
+            vec![
+                (
+                    "shapes.bzs",
+                    String::from("export class Square {\n    var side = 0;\n    fun(s) => {\n        soul.side = s\n    }\n    fun area() => soul.side * soul.side\n}\nexport val unit = 1"),
+                ),
+                (
+                    "sizes.bzs",
+                    String::from("import { Square, unit as one } from \"shapes.bzs\"\nexport fun square(s) => new Square(s + one)"),
+                ),
+            ],
+            String::from("import * as shapes from \"shapes.bzs\"\nimport { square } from \"sizes.bzs\"\n[square(2).area(), shapes.unit, classof(square(0)) == shapes.Square]"),
+        ),
+        Ok(String::from("[9, 1, true]"))
+    );
+}
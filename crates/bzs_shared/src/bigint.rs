@@ -0,0 +1,686 @@
+/*
+   Copyright 2021 BlazifyOrg
+   Licensed under the Apache License, Version 2.0 (the "License");
+   you may not use this file except in compliance with the License.
+   You may obtain a copy of the License at
+       http://www.apache.org/licenses/LICENSE-2.0
+   Unless required by applicable law or agreed to in writing, software
+   distributed under the License is distributed on an "AS IS" BASIS,
+   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+   See the License for the specific language governing permissions and
+   limitations under the License.
+*/
+
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::convert::TryFrom;
+use std::fmt;
+
+// Limbs are base 10^9 so printing and parsing don't need any division
+const BASE: u64 = 1_000_000_000;
+
+// An integer of any size, stored as a sign and its digits in base 10^9 with the least
+// significant one first and no leading zeros, so zero has no digits at all
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct BigInt {
+    negative: bool,
+    limbs: Vec<u32>,
+}
+
+fn trim(mut limbs: Vec<u32>) -> Vec<u32> {
+    while limbs.last() == Some(&0) {
+        limbs.pop();
+    }
+    limbs
+}
+
+fn cmp_mag(a: &[u32], b: &[u32]) -> Ordering {
+    a.len()
+        .cmp(&b.len())
+        .then_with(|| a.iter().rev().cmp(b.iter().rev()))
+}
+
+fn add_mag(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let mut res = vec![];
+    let mut carry = 0;
+    for i in 0..a.len().max(b.len()) {
+        let sum = *a.get(i).unwrap_or(&0) as u64 + *b.get(i).unwrap_or(&0) as u64 + carry;
+        res.push((sum % BASE) as u32);
+        carry = sum / BASE;
+    }
+    if carry > 0 {
+        res.push(carry as u32);
+    }
+    res
+}
+
+// `a` has to be at least as large as `b`
+fn sub_mag(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let mut res = vec![];
+    let mut borrow = 0;
+    for (i, limb) in a.iter().enumerate() {
+        let sub = *b.get(i).unwrap_or(&0) as i64 + borrow;
+        let mut diff = *limb as i64 - sub;
+        borrow = 0;
+        if diff < 0 {
+            diff += BASE as i64;
+            borrow = 1;
+        }
+        res.push(diff as u32);
+    }
+    trim(res)
+}
+
+fn mul_mag(a: &[u32], b: &[u32]) -> Vec<u32> {
+    if a.is_empty() || b.is_empty() {
+        return vec![];
+    }
+    let mut res = vec![0u64; a.len() + b.len()];
+    for (i, x) in a.iter().enumerate() {
+        let mut carry = 0;
+        for (j, y) in b.iter().enumerate() {
+            let cur = res[i + j] + *x as u64 * *y as u64 + carry;
+            res[i + j] = cur % BASE;
+            carry = cur / BASE;
+        }
+        let mut k = i + b.len();
+        while carry > 0 {
+            let cur = res[k] + carry;
+            res[k] = cur % BASE;
+            carry = cur / BASE;
+            k += 1;
+        }
+    }
+    trim(res.into_iter().map(|l| l as u32).collect())
+}
+
+fn mul_short(a: &[u32], factor: u64) -> Vec<u32> {
+    let mut res = vec![];
+    let mut carry = 0;
+    for limb in a {
+        let cur = *limb as u64 * factor + carry;
+        res.push((cur % BASE) as u32);
+        carry = cur / BASE;
+    }
+    if carry > 0 {
+        res.push(carry as u32);
+    }
+    res
+}
+
+// Schoolbook long division one base 10^9 digit at a time, each digit is estimated from
+// the top two digits of the remainder and the top digit of the divisor
+fn divmod_mag(a: &[u32], b: &[u32]) -> (Vec<u32>, Vec<u32>) {
+    if cmp_mag(a, b) == Ordering::Less {
+        return (vec![], a.to_vec());
+    }
+    if b.len() == 1 {
+        let (quotient, rem) = short_divmod(a, b[0] as u64);
+        return (quotient, trim(vec![rem as u32]));
+    }
+    // Scaling both so the top digit of the divisor is at least half the base keeps each
+    // estimate at most two above the real digit
+    let scale = BASE / (*b.last().unwrap() as u64 + 1);
+    let b = mul_short(b, scale);
+    let mut rem = mul_short(a, scale);
+    rem.resize(a.len() + 1, 0);
+    let n = b.len();
+    let (top, second) = (b[n - 1] as u64, b[n - 2] as u64);
+    let mut quotient = vec![0; a.len() - n + 1];
+    for j in (0..quotient.len()).rev() {
+        let head = rem[j + n] as u64 * BASE + rem[j + n - 1] as u64;
+        let (mut digit, mut rest) = (head / top, head % top);
+        while digit >= BASE || digit * second > rest * BASE + rem[j + n - 2] as u64 {
+            digit -= 1;
+            rest += top;
+            if rest >= BASE {
+                break;
+            }
+        }
+
+        let (mut carry, mut borrow) = (0, 0);
+        for i in 0..n {
+            let product = digit * b[i] as u64 + carry;
+            carry = product / BASE;
+            let diff = rem[i + j] as i64 - (product % BASE) as i64 - borrow;
+            borrow = (diff < 0) as i64;
+            rem[i + j] = (diff + borrow * BASE as i64) as u32;
+        }
+        let diff = rem[j + n] as i64 - carry as i64 - borrow;
+        if diff < 0 {
+            // the estimate was still one too large, so one divisor is added back
+            digit -= 1;
+            let mut carry = 0;
+            for i in 0..n {
+                let sum = rem[i + j] as u64 + b[i] as u64 + carry;
+                rem[i + j] = (sum % BASE) as u32;
+                carry = sum / BASE;
+            }
+            rem[j + n] = 0;
+        } else {
+            rem[j + n] = diff as u32;
+        }
+        quotient[j] = digit as u32;
+    }
+    rem.truncate(n);
+    let (rem, _) = short_divmod(&trim(rem), scale);
+    (trim(quotient), rem)
+}
+
+// Divides by a divisor of at most 2^32, giving the quotient and the remainder
+fn short_divmod(a: &[u32], divisor: u64) -> (Vec<u32>, u64) {
+    let mut quotient = vec![0; a.len()];
+    let mut rem = 0;
+    for i in (0..a.len()).rev() {
+        let cur = rem * BASE + a[i] as u64;
+        quotient[i] = (cur / divisor) as u32;
+        rem = cur % divisor;
+    }
+    (trim(quotient), rem)
+}
+
+// A magnitude in base 2^32, the bitwise operators work on those
+fn to_words(limbs: &[u32]) -> Vec<u32> {
+    let mut words = vec![];
+    let mut rest = limbs.to_vec();
+    while !rest.is_empty() {
+        let (next, word) = short_divmod(&rest, 1 << 32);
+        words.push(word as u32);
+        rest = next;
+    }
+    words
+}
+
+fn from_words(words: &[u32]) -> Vec<u32> {
+    let radix = [((1u64 << 32) % BASE) as u32, ((1u64 << 32) / BASE) as u32];
+    let mut limbs = vec![];
+    for word in words.iter().rev() {
+        let word = [word % BASE as u32, word / BASE as u32];
+        limbs = add_mag(&mul_mag(&limbs, &radix), &trim(word.to_vec()));
+    }
+    trim(limbs)
+}
+
+impl BigInt {
+    fn new(negative: bool, limbs: Vec<u32>) -> Self {
+        let limbs = trim(limbs);
+        Self {
+            negative: negative && !limbs.is_empty(),
+            limbs,
+        }
+    }
+
+    pub fn from_i128(i: i128) -> Self {
+        let mut mag = i.unsigned_abs();
+        let mut limbs = vec![];
+        while mag > 0 {
+            limbs.push((mag % BASE as u128) as u32);
+            mag /= BASE as u128;
+        }
+        Self::new(i < 0, limbs)
+    }
+
+    // Parses decimal digits with an optional leading minus
+    pub fn parse(s: &str) -> Option<Self> {
+        let (negative, digits) = match s.strip_prefix('-') {
+            Some(digits) => (true, digits),
+            None => (false, s),
+        };
+        if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+            return None;
+        }
+        let mut limbs = vec![];
+        let mut end = digits.len();
+        while end > 0 {
+            let start = end.saturating_sub(9);
+            limbs.push(digits[start..end].parse().ok()?);
+            end = start;
+        }
+        Some(Self::new(negative, limbs))
+    }
+
+    pub fn to_i128(&self) -> Option<i128> {
+        let mut mag: u128 = 0;
+        for limb in self.limbs.iter().rev() {
+            mag = mag.checked_mul(BASE as u128)?.checked_add(*limb as u128)?;
+        }
+        if self.negative {
+            0i128.checked_sub_unsigned(mag)
+        } else {
+            i128::try_from(mag).ok()
+        }
+    }
+
+    // The nearest f64, adding up the limbs as floats would round at every step
+    pub fn to_f64(&self) -> f64 {
+        self.to_string().parse().unwrap()
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.limbs.is_empty()
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.negative
+    }
+
+    pub fn neg(&self) -> Self {
+        Self::new(!self.negative, self.limbs.clone())
+    }
+
+    pub fn add(&self, other: &Self) -> Self {
+        if self.negative == other.negative {
+            return Self::new(self.negative, add_mag(&self.limbs, &other.limbs));
+        }
+        match cmp_mag(&self.limbs, &other.limbs) {
+            Ordering::Less => Self::new(other.negative, sub_mag(&other.limbs, &self.limbs)),
+            _ => Self::new(self.negative, sub_mag(&self.limbs, &other.limbs)),
+        }
+    }
+
+    pub fn sub(&self, other: &Self) -> Self {
+        self.add(&other.neg())
+    }
+
+    pub fn mul(&self, other: &Self) -> Self {
+        Self::new(
+            self.negative != other.negative,
+            mul_mag(&self.limbs, &other.limbs),
+        )
+    }
+
+    // Division rounding toward zero with the remainder taking the sign of `self`,
+    // None when dividing by zero
+    pub fn div_rem(&self, other: &Self) -> Option<(Self, Self)> {
+        if other.is_zero() {
+            return None;
+        }
+        let (quotient, rem) = divmod_mag(&self.limbs, &other.limbs);
+        Some((
+            Self::new(self.negative != other.negative, quotient),
+            Self::new(self.negative, rem),
+        ))
+    }
+
+    // Division rounding toward negative infinity with the remainder taking the sign of
+    // `other`, None when dividing by zero
+    pub fn div_mod(&self, other: &Self) -> Option<(Self, Self)> {
+        let (quot, rem) = self.div_rem(other)?;
+        if !rem.is_zero() && rem.negative != other.negative {
+            Some((quot.sub(&Self::from_i128(1)), rem.add(other)))
+        } else {
+            Some((quot, rem))
+        }
+    }
+
+    pub fn pow(&self, mut exp: u32) -> Self {
+        let mut base = self.clone();
+        let mut res = Self::from_i128(1);
+        while exp > 0 {
+            if exp & 1 == 1 {
+                res = res.mul(&base);
+            }
+            exp >>= 1;
+            if exp > 0 {
+                base = base.mul(&base);
+            }
+        }
+        res
+    }
+
+    pub fn shl(&self, shift: u32) -> Self {
+        let (whole, bits) = ((shift / 32) as usize, shift % 32);
+        let mut res = vec![0; whole];
+        let mut carry = 0;
+        for word in to_words(&self.limbs) {
+            if bits == 0 {
+                res.push(word);
+            } else {
+                res.push((word << bits) | carry);
+                carry = word >> (32 - bits);
+            }
+        }
+        res.push(carry);
+        Self::new(self.negative, from_words(&res))
+    }
+
+    // Rounds toward negative infinity like an Int shift
+    pub fn shr(&self, shift: u32) -> Self {
+        let words = to_words(&self.limbs);
+        let (whole, bits) = ((shift / 32) as usize, shift % 32);
+        if whole >= words.len() {
+            return Self::from_i128(if self.negative { -1 } else { 0 });
+        }
+        let res = (whole..words.len())
+            .map(|i| match (bits, words.get(i + 1)) {
+                (0, _) | (_, None) => words[i] >> bits,
+                (_, Some(next)) => (words[i] >> bits) | (next << (32 - bits)),
+            })
+            .collect::<Vec<u32>>();
+        let lost = words[..whole].iter().any(|w| *w != 0) || words[whole] & ((1 << bits) - 1) != 0;
+        if self.negative && lost {
+            Self::new(true, add_mag(&from_words(&res), &[1]))
+        } else {
+            Self::new(self.negative, from_words(&res))
+        }
+    }
+
+    pub fn not(&self) -> Self {
+        self.neg().sub(&Self::from_i128(1))
+    }
+
+    pub fn bit_and(&self, other: &Self) -> Self {
+        self.bitwise(other, |a, b| a & b)
+    }
+
+    pub fn bit_or(&self, other: &Self) -> Self {
+        self.bitwise(other, |a, b| a | b)
+    }
+
+    pub fn bit_xor(&self, other: &Self) -> Self {
+        self.bitwise(other, |a, b| a ^ b)
+    }
+
+    // Applies `op` to the two's complement of both numbers, negative numbers are
+    // extended with ones on the left as far as needed
+    fn bitwise(&self, other: &Self, op: fn(u32, u32) -> u32) -> Self {
+        let words = |x: &Self| -> Vec<u32> {
+            if x.negative {
+                let below = sub_mag(&x.limbs, &[1]);
+                to_words(&below).iter().map(|w| !w).collect()
+            } else {
+                to_words(&x.limbs)
+            }
+        };
+        let fill = |x: &Self| if x.negative { u32::MAX } else { 0 };
+        let (a, b) = (words(self), words(other));
+        let res = (0..a.len().max(b.len()))
+            .map(|i| {
+                op(
+                    *a.get(i).unwrap_or(&fill(self)),
+                    *b.get(i).unwrap_or(&fill(other)),
+                )
+            })
+            .collect::<Vec<u32>>();
+        if op(fill(self), fill(other)) == 0 {
+            return Self::new(false, from_words(&res));
+        }
+        let below = from_words(&res.iter().map(|w| !w).collect::<Vec<u32>>());
+        Self::new(true, add_mag(&below, &[1]))
+    }
+}
+
+impl PartialOrd for BigInt {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for BigInt {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self.negative, other.negative) {
+            (false, true) => Ordering::Greater,
+            (true, false) => Ordering::Less,
+            (false, false) => cmp_mag(&self.limbs, &other.limbs),
+            (true, true) => cmp_mag(&other.limbs, &self.limbs),
+        }
+    }
+}
+
+impl fmt::Display for BigInt {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut limbs = self.limbs.iter().rev();
+        match limbs.next() {
+            Some(first) => {
+                if self.negative {
+                    write!(f, "-")?;
+                }
+                write!(f, "{}", first)?;
+                for limb in limbs {
+                    write!(f, "{:09}", limb)?;
+                }
+                Ok(())
+            }
+            None => write!(f, "0"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{cmp_mag, BigInt};
+    use std::cmp::Ordering;
+
+    const SAMPLES: [i128; 12] = [
+        0,
+        1,
+        -1,
+        7,
+        -7,
+        999_999_999,
+        -1_000_000_000,
+        123_456_789_012_345_678,
+        -987_654_321_987_654_321,
+        i64::MAX as i128,
+        i64::MIN as i128,
+        1 << 100,
+    ];
+
+    fn big(i: i128) -> BigInt {
+        BigInt::from_i128(i)
+    }
+
+    fn floor_div_mod(a: i128, b: i128) -> (i128, i128) {
+        let (quot, rem) = (a / b, a % b);
+        if rem != 0 && (rem < 0) != (b < 0) {
+            (quot - 1, rem + b)
+        } else {
+            (quot, rem)
+        }
+    }
+
+    #[test]
+    fn arithmetic_matches_i128() {
+        for a in SAMPLES {
+            for b in SAMPLES {
+                assert_eq!(big(a).add(&big(b)), big(a + b), "{} + {}", a, b);
+                assert_eq!(big(a).sub(&big(b)), big(a - b), "{} - {}", a, b);
+                if let Some(prod) = a.checked_mul(b) {
+                    assert_eq!(big(a).mul(&big(b)), big(prod), "{} * {}", a, b);
+                }
+                if b == 0 {
+                    assert!(big(a).div_rem(&big(b)).is_none());
+                    assert!(big(a).div_mod(&big(b)).is_none());
+                    continue;
+                }
+                assert_eq!(
+                    big(a).div_rem(&big(b)),
+                    Some((big(a / b), big(a % b))),
+                    "{} / {}",
+                    a,
+                    b
+                );
+                let (quot, rem) = floor_div_mod(a, b);
+                assert_eq!(
+                    big(a).div_mod(&big(b)),
+                    Some((big(quot), big(rem))),
+                    "{} // {}",
+                    a,
+                    b
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn floor_division_with_mixed_signs() {
+        let cases = [
+            (7, 2, 3, 1),
+            (-7, 2, -4, 1),
+            (7, -2, -4, -1),
+            (-7, -2, 3, -1),
+        ];
+        for (a, b, quot, rem) in cases {
+            assert_eq!(big(a).div_mod(&big(b)), Some((big(quot), big(rem))));
+        }
+        assert_eq!(big(-6).div_mod(&big(3)), Some((big(-2), big(0))));
+    }
+
+    #[test]
+    fn to_f64_rounds_to_nearest() {
+        let ten = big(10);
+        assert_eq!(ten.pow(40).to_f64(), 1e40);
+        assert_eq!(ten.pow(22).to_f64(), 1e22);
+        assert_eq!(big(2).pow(200).to_f64(), 2f64.powi(200));
+        assert_eq!(big(2).pow(1024).to_f64(), f64::INFINITY);
+        assert_eq!(big(2).pow(1024).neg().to_f64(), f64::NEG_INFINITY);
+        // halfway between two floats rounds to the even one
+        assert_eq!(big(2).pow(53).add(&big(1)).to_f64(), 2f64.powi(53));
+        assert_eq!(big(2).pow(53).add(&big(3)).to_f64(), 2f64.powi(53) + 4.0);
+        assert_eq!(
+            big(-123_456_789_012_345_678).to_f64(),
+            -123_456_789_012_345_678.0
+        );
+        assert_eq!(big(0).to_f64(), 0.0);
+    }
+
+    // Numbers of up to 40 digits spread over a few limbs, with some runs of zeros and
+    // nines in there as those are where the quotient estimates go wrong
+    fn samples() -> Vec<BigInt> {
+        let mut state: u64 = 0x2545_f491_4f6c_dd1d;
+        let mut next = || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state
+        };
+        let mut res = vec![];
+        for _ in 0..60 {
+            let digits = (next() % 40 + 1) as usize;
+            let mut s = String::new();
+            for _ in 0..digits {
+                s.push(match next() % 4 {
+                    0 => '0',
+                    1 => '9',
+                    _ => (b'0' + (next() % 10) as u8) as char,
+                });
+            }
+            let n = BigInt::parse(&s).unwrap();
+            res.push(if next() % 2 == 0 { n } else { n.neg() });
+        }
+        res
+    }
+
+    #[test]
+    fn long_division() {
+        for a in samples() {
+            for b in samples().iter().filter(|b| !b.is_zero()) {
+                let (quot, rem) = a.div_rem(b).unwrap();
+                assert_eq!(quot.mul(b).add(&rem), a, "{} / {}", a, b);
+                assert!(rem.is_zero() || rem.is_negative() == a.is_negative());
+                assert_eq!(
+                    cmp_mag(&rem.limbs, &b.limbs),
+                    Ordering::Less,
+                    "{} % {}",
+                    a,
+                    b
+                );
+            }
+        }
+        let ten = big(10);
+        assert_eq!(
+            ten.pow(40).sub(&big(1)).div_rem(&ten.pow(20).sub(&big(1))),
+            Some((ten.pow(20).add(&big(1)), big(0)))
+        );
+    }
+
+    #[test]
+    fn shifts_match_powers_of_two() {
+        for a in samples() {
+            for shift in [0, 1, 31, 32, 33, 64, 95, 200] {
+                let power = big(2).pow(shift);
+                assert_eq!(a.shl(shift), a.mul(&power), "{} << {}", a, shift);
+                assert_eq!(
+                    a.shr(shift),
+                    a.div_mod(&power).unwrap().0,
+                    "{} >> {}",
+                    a,
+                    shift
+                );
+                assert_eq!(a.shl(shift).shr(shift), a);
+            }
+        }
+    }
+
+    #[test]
+    fn i128_boundaries() {
+        for i in [i128::MAX, i128::MIN, i128::MAX - 1, i128::MIN + 1] {
+            assert_eq!(big(i).to_i128(), Some(i));
+            assert_eq!(big(i).to_string(), i.to_string());
+        }
+        let max = big(i128::MAX);
+        let min = big(i128::MIN);
+        assert_eq!(max.add(&big(1)).to_i128(), None);
+        assert_eq!(min.sub(&big(1)).to_i128(), None);
+        assert_eq!(max.add(&big(1)), min.neg());
+        assert_eq!(
+            min.neg().to_string(),
+            "170141183460469231731687303715884105728"
+        );
+        assert_eq!(
+            max.mul(&max).to_string(),
+            "28948022309329048855892746252171976962977213799489202546401021394546514198529"
+        );
+        assert_eq!(min.div_rem(&big(-1)), Some((min.neg(), big(0))));
+    }
+
+    #[test]
+    fn parse_and_format_round_trip() {
+        for s in [
+            "0",
+            "1",
+            "-1",
+            "999999999",
+            "1000000000",
+            "-1000000001",
+            "123456789012345678901234567890",
+            "-100000000000000000000000000000000000000000",
+        ] {
+            assert_eq!(BigInt::parse(s).unwrap().to_string(), s);
+        }
+        for i in SAMPLES {
+            assert_eq!(BigInt::parse(&i.to_string()), Some(big(i)));
+        }
+        assert_eq!(BigInt::parse("-0"), Some(big(0)));
+        assert_eq!(BigInt::parse("000042").unwrap().to_string(), "42");
+        for s in ["", "-", "1.5", "+1", "12a", "--1"] {
+            assert_eq!(BigInt::parse(s), None, "{:?}", s);
+        }
+    }
+
+    #[test]
+    fn shifts_and_bitwise() {
+        for a in SAMPLES {
+            for b in SAMPLES {
+                assert_eq!(big(a).bit_and(&big(b)), big(a & b), "{} & {}", a, b);
+                assert_eq!(big(a).bit_or(&big(b)), big(a | b), "{} | {}", a, b);
+                assert_eq!(big(a).bit_xor(&big(b)), big(a ^ b), "{} ^ {}", a, b);
+            }
+            assert_eq!(big(a).not(), big(!a));
+            for shift in [0, 1, 31, 64, 100, 200] {
+                assert_eq!(
+                    big(a).shr(shift),
+                    big(a >> shift.min(127)),
+                    "{} >> {}",
+                    a,
+                    shift
+                );
+            }
+        }
+        assert_eq!(
+            big(1).shl(130).to_string(),
+            "1361129467683753853853498429727072845824"
+        );
+        assert_eq!(big(-3).shl(130).shr(130), big(-3));
+    }
+}
@@ -18,6 +18,9 @@ use codespan_reporting::term::{self};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fmt::{Display, Error as E, Formatter};
+
+mod bigint;
+pub use bigint::BigInt;
 #[derive(Debug, Copy, Clone, PartialEq)]
 pub enum Tokens {
     Int,
@@ -28,11 +31,25 @@ pub enum Tokens {
     Colon,
     Comma,
     Dot,
+    DoubleDot,
+    DoubleDotEquals,
+    TripleDot,
+    QuestionDot,
+    DoubleQuestion,
+    Hash,
     Arrow,
     Plus,
     Minus,
     Multiply,
     Divide,
+    FloorDivide,
+    Modulo,
+    BitAnd,
+    BitOr,
+    BitXor,
+    BitNot,
+    LeftShift,
+    RightShift,
     LeftParenthesis,
     RightParenthesis,
     LeftCurlyBraces,
@@ -43,6 +60,12 @@ pub enum Tokens {
     Keyword,
     Identifier,
     Equals,
+    PlusEquals,
+    MinusEquals,
+    MultiplyEquals,
+    DivideEquals,
+    ModuloEquals,
+    PowerEquals,
     DoubleEquals,
     NotEquals,
     LessThan,
@@ -57,6 +80,7 @@ pub enum Tokens {
 #[derive(Debug, PartialEq, Clone)]
 pub enum DynType {
     Int(i128),
+    BigInt(BigInt),
     Float(f64),
     String(String),
     Char(char),
@@ -111,14 +135,95 @@ pub enum Constants {
     None,
     Null,
     Int(i128),
+    BigInt(BigInt),
     Float(f64),
     String(String),
     Char(char),
     Boolean(bool),
-    Function(Vec<u16>, ByteCode),
+    Function(Option<u16>, Params, ByteCode),
     RawArray(Vec<ByteCode>),
     RawObject(HashMap<usize, ByteCode>),
-    RawClass(Option<(Vec<u16>, ByteCode)>, HashMap<usize, ByteCode>),
+    RawClass(ClassTemplate),
+    Module(Vec<u16>, Vec<u16>, ByteCode),
+    Enum(EnumTemplate),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct EnumTemplate {
+    pub name: u16,
+    pub variants: Vec<(u16, Vec<u16>)>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ClassTemplate {
+    pub name: u16,
+    pub constructor: Option<(Params, ByteCode)>,
+    pub props: HashMap<usize, ByteCode>,
+    pub statics: HashMap<usize, ByteCode>,
+    pub getters: HashMap<usize, ByteCode>,
+    pub setters: HashMap<usize, ByteCode>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Params {
+    pub names: Vec<u16>,
+    pub defaults: Vec<Option<ByteCode>>,
+    pub rest: Option<u16>,
+}
+
+impl Params {
+    pub fn required(&self) -> usize {
+        self.defaults.iter().take_while(|d| d.is_none()).count()
+    }
+
+    pub fn arity(&self) -> String {
+        let required = self.required();
+        if self.rest.is_some() {
+            format!("at least {}", required)
+        } else if required == self.names.len() {
+            required.to_string()
+        } else {
+            format!("{} to {}", required, self.names.len())
+        }
+    }
+
+    // Validates a call passing `positional` args followed by the `named` ones
+    pub fn check(
+        &self,
+        positional: usize,
+        named: &[u16],
+        name_of: impl Fn(u16) -> String,
+    ) -> Result<(), String> {
+        if self.rest.is_none() && positional > self.names.len() {
+            return Err(format!(
+                "Expected {} args but found {}",
+                self.arity(),
+                positional
+            ));
+        }
+        for (i, id) in named.iter().enumerate() {
+            match self.names.iter().position(|n| n == id) {
+                None => return Err(format!("Unknown argument '{}'", name_of(*id))),
+                Some(idx) if idx < positional || named[..i].contains(id) => {
+                    return Err(format!("Argument '{}' given more than once", name_of(*id)))
+                }
+                _ => (),
+            }
+        }
+        for id in self.names.iter().take(self.required()).skip(positional) {
+            if named.is_empty() {
+                return Err(format!(
+                    "Expected {} args but found {}",
+                    self.arity(),
+                    positional
+                ));
+            }
+            if !named.contains(id) {
+                return Err(format!("Missing argument '{}'", name_of(*id)));
+            }
+        }
+        Ok(())
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -145,10 +250,18 @@ impl Error {
     }
 
     pub fn prettify(&self) {
+        self.emit(Diagnostic::error());
+    }
+
+    pub fn warn(&self) {
+        self.emit(Diagnostic::warning());
+    }
+
+    fn emit(&self, diagnostic: Diagnostic<usize>) {
         let mut files = SimpleFiles::new();
         let file_id = files.add(self.pos_start.file_name, self.pos_start.file_content);
 
-        let diagnostic = Diagnostic::error()
+        let diagnostic = diagnostic
             .with_message(self.name)
             .with_labels(vec![Label::primary(
                 file_id,
@@ -208,11 +321,37 @@ impl Token {
     }
 }
 
+#[derive(Debug, Clone, PartialEq)]
+pub enum Pattern {
+    Wildcard(Token),
+    Literal(Token),
+    Binding(Token),
+    Array(Token, Vec<Pattern>, Option<Token>),
+    Object(Token, Vec<(Token, Pattern)>),
+    Variant(Token, Token, Vec<Pattern>),
+    Or(Vec<Pattern>),
+}
+
+impl Pattern {
+    pub fn token(&self) -> &Token {
+        match self {
+            Pattern::Wildcard(token)
+            | Pattern::Literal(token)
+            | Pattern::Binding(token)
+            | Pattern::Array(token, ..)
+            | Pattern::Object(token, _)
+            | Pattern::Variant(token, ..) => token,
+            Pattern::Or(alternatives) => alternatives[0].token(),
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum Node {
     WhileNode {
         condition_node: Box<Node>,
         body_node: Box<Node>,
+        label: Option<Token>,
     },
     VarReassignNode {
         name: Token,
@@ -244,6 +383,14 @@ pub enum Node {
         name: Option<Token>,
         body_node: Box<Node>,
         arg_tokens: Vec<Token>,
+        defaults: Vec<Option<Node>>,
+        rest: Option<Token>,
+    },
+    ForInNode {
+        var_name_token: Token,
+        iterable: Box<Node>,
+        body_node: Box<Node>,
+        label: Option<Token>,
     },
     ForNode {
         var_name_token: Token,
@@ -251,6 +398,15 @@ pub enum Node {
         end_value: Box<Node>,
         body_node: Box<Node>,
         step_value_node: Box<Node>,
+        label: Option<Token>,
+    },
+    BreakNode {
+        token: Token,
+        label: Option<Token>,
+    },
+    ContinueNode {
+        token: Token,
+        label: Option<Token>,
     },
     CharNode {
         token: Token,
@@ -258,10 +414,27 @@ pub enum Node {
     CallNode {
         node_to_call: Box<Node>,
         args: Vec<Node>,
+        named: Vec<(Token, Node)>,
     },
     BooleanNode {
         token: Token,
     },
+    NullNode {
+        token: Token,
+    },
+    MapNode {
+        entries: Vec<(Node, Node)>,
+    },
+    DeleteNode {
+        token: Token,
+        target: Box<Node>,
+    },
+    OptionalChainNode {
+        chain: Box<Node>,
+    },
+    NullSafeNode {
+        node: Box<Node>,
+    },
     BinOpNode {
         left: Box<Node>,
         right: Box<Node>,
@@ -274,12 +447,45 @@ pub enum Node {
         array: Box<Node>,
         index: Box<Node>,
     },
+    ArrayIndexEdit {
+        array: Box<Node>,
+        index: Box<Node>,
+        new_val: Box<Node>,
+    },
     Statements {
         statements: Vec<Node>,
     },
     ReturnNode {
         value: Box<Option<Node>>,
     },
+    ThrowNode {
+        token: Token,
+        value: Box<Node>,
+    },
+    TryNode {
+        try_body: Box<Node>,
+        catch_var: Option<Token>,
+        catch_body: Box<Option<Node>>,
+        finally_body: Box<Option<Node>>,
+    },
+    MatchNode {
+        value: Box<Node>,
+        arms: Vec<(Pattern, Option<Node>, Node)>,
+    },
+    DestructureNode {
+        pattern: Pattern,
+        value: Box<Node>,
+        reassignable: bool,
+    },
+    ImportNode {
+        path: Token,
+        names: Vec<(Token, Token)>,
+        namespace: Option<Token>,
+    },
+    ExportNode {
+        token: Token,
+        node: Box<Node>,
+    },
     ObjectDefNode {
         properties: Vec<(Token, Node)>,
     },
@@ -292,15 +498,29 @@ pub enum Node {
         property: Token,
         new_val: Box<Node>,
     },
+    CompoundAssignNode {
+        target: Box<Node>,
+        op_token: Token,
+        value: Box<Node>,
+    },
     ClassDefNode {
         name: Token,
-        constructor: Box<Option<(Vec<Token>, Node)>>,
+        parent: Option<Token>,
+        constructor: Box<Option<Node>>,
         properties: Vec<(Token, Node)>,
         methods: Vec<(Token, Node)>,
+        statics: Vec<(Token, Node)>,
+        getters: Vec<(Token, Node)>,
+        setters: Vec<(Token, Node)>,
+    },
+    EnumDefNode {
+        name: Token,
+        variants: Vec<(Token, Vec<Token>)>,
     },
     ClassInitNode {
         name: Token,
         constructor_params: Vec<Node>,
+        named: Vec<(Token, Node)>,
     },
 }
 
@@ -11,8 +11,14 @@
    limitations under the License.
 */
 
-use bzs_shared::{ByteCode, Constants, DynType, Node, Tokens};
-use std::collections::HashMap;
+use bzs_shared::{
+    ByteCode, ClassTemplate, Constants, DynType, EnumTemplate, Error, Node, Params, Pattern, Token,
+    Tokens,
+};
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::iter::once;
+use std::rc::Rc;
 
 #[derive(Debug)]
 pub enum OpCode {
@@ -24,6 +30,14 @@ pub enum OpCode {
     OpMultiply,
     OpDivide,
     OpPower,
+    OpModulo,
+    OpFloorDivide,
+    OpBitAnd,
+    OpBitOr,
+    OpBitXor,
+    OpBitNot,
+    OpLeftShift,
+    OpRightShift,
     OpNot,
     OpAnd,
     OpOr,
@@ -42,10 +56,33 @@ pub enum OpCode {
     OpBlockStart,
     OpBlockEnd,
     OpIndexArray,
+    OpIndexAssign,
     OpPropertyAccess(u16),
     OpPropertyAssign(u16),
     OpReturn,
     OpPop,
+    OpDup,
+    OpDupTwo,
+    OpIterStart,
+    OpIterNext(u16),
+    OpRange,
+    OpRangeInclusive,
+    OpTry(u16),
+    OpEndTry,
+    OpThrow,
+    OpInherit,
+    OpInstanceOf,
+    OpCallNamed,
+    OpMatchEquals,
+    OpMatchArray(u16),
+    OpMatchArrayMin(u16),
+    OpArraySlice(u16),
+    OpHasProperty(u16),
+    OpNoMatch,
+    OpMatchVariant(u16),
+    OpJumpIfNull(u16),
+    OpMap(u16),
+    OpDelete,
 }
 
 impl OpCode {
@@ -81,6 +118,37 @@ impl OpCode {
             Self::OpPropertyAccess(i) => make_three_byte_op(0x3A, *i),
             Self::OpPropertyAssign(i) => make_three_byte_op(0x3B, *i),
             Self::OpReturn => vec![0x3C],
+            Self::OpModulo => vec![0x3D],
+            Self::OpFloorDivide => vec![0x3E],
+            Self::OpBitAnd => vec![0x3F],
+            Self::OpBitOr => vec![0x4A],
+            Self::OpBitXor => vec![0x4B],
+            Self::OpBitNot => vec![0x4C],
+            Self::OpLeftShift => vec![0x4D],
+            Self::OpRightShift => vec![0x4E],
+            Self::OpDup => vec![0x4F],
+            Self::OpIndexAssign => vec![0x5A],
+            Self::OpDupTwo => vec![0x5B],
+            Self::OpIterStart => vec![0x5C],
+            Self::OpIterNext(to) => make_three_byte_op(0x5D, *to),
+            Self::OpRange => vec![0x5E],
+            Self::OpRangeInclusive => vec![0x5F],
+            Self::OpTry(to) => make_three_byte_op(0x6A, *to),
+            Self::OpEndTry => vec![0x6B],
+            Self::OpThrow => vec![0x6C],
+            Self::OpInherit => vec![0x6D],
+            Self::OpInstanceOf => vec![0x6E],
+            Self::OpCallNamed => vec![0x6F],
+            Self::OpMatchEquals => vec![0x7A],
+            Self::OpMatchArray(len) => make_three_byte_op(0x7B, *len),
+            Self::OpMatchArrayMin(len) => make_three_byte_op(0x7C, *len),
+            Self::OpArraySlice(start) => make_three_byte_op(0x7D, *start),
+            Self::OpHasProperty(i) => make_three_byte_op(0x7E, *i),
+            Self::OpNoMatch => vec![0x7F],
+            Self::OpMatchVariant(i) => make_three_byte_op(0x8A, *i),
+            Self::OpJumpIfNull(to) => make_three_byte_op(0x8B, *to),
+            Self::OpMap(len) => make_three_byte_op(0x8C, *len),
+            Self::OpDelete => vec![0x8D],
         }
     }
 }
@@ -95,10 +163,271 @@ fn make_three_byte_op(code: u8, data: u16) -> Vec<u8> {
     output
 }
 
+#[derive(Debug, Clone)]
+struct Loop {
+    label: Option<String>,
+    scope_depth: usize,
+    try_depth: usize,
+    start: u16,
+    breaks: Vec<u16>,
+}
+
+// Where a `?.` jumps out of its optional chain when the value before it is null, along
+// with how many values each call it's the callee of has pushed, as those and the blocks
+// the calls opened have to be left behind
+#[derive(Debug, Clone)]
+struct Chain {
+    calls: usize,
+    skips: Vec<(u16, Vec<usize>)>,
+}
+
+#[derive(Debug, Clone)]
+struct TryFrame {
+    scope_depth: usize,
+    finally: Option<Node>,
+}
+
+// A step from a matched value to the part of it a pattern looks at
+#[derive(Debug, Clone)]
+enum Step {
+    Index(usize),
+    Property(u16),
+    Slice(u16),
+}
+
+// Whether every value matching `later` also matches `earlier`, as far as it's obvious
+fn covers(earlier: &Pattern, later: &Pattern) -> bool {
+    match (earlier, later) {
+        (Pattern::Wildcard(_), _) | (Pattern::Binding(_), _) => true,
+        (_, Pattern::Or(alternatives)) => alternatives.iter().all(|a| covers(earlier, a)),
+        (Pattern::Or(alternatives), _) => alternatives.iter().any(|a| covers(a, later)),
+        (Pattern::Literal(a), Pattern::Literal(b)) => a.r#type == b.r#type && a.value == b.value,
+        (Pattern::Variant(a, x, fields), Pattern::Variant(b, y, other_fields)) => {
+            a.value == b.value
+                && x.value == y.value
+                && fields.len() == other_fields.len()
+                && fields.iter().zip(other_fields).all(|(f, o)| covers(f, o))
+        }
+        _ => false,
+    }
+}
+
+fn pattern_bindings(pattern: &Pattern, counts: &mut HashMap<String, usize>) {
+    match pattern {
+        Pattern::Binding(name) => *counts.entry(name.value.into_string()).or_insert(0) += 1,
+        Pattern::Array(_, elements, rest) => {
+            for element in elements {
+                pattern_bindings(element, counts);
+            }
+            if let Some(rest) = rest {
+                *counts.entry(rest.value.into_string()).or_insert(0) += 1;
+            }
+        }
+        Pattern::Object(_, entries) => {
+            for (_, value) in entries {
+                pattern_bindings(value, counts);
+            }
+        }
+        Pattern::Variant(_, _, fields) => {
+            for field in fields {
+                pattern_bindings(field, counts);
+            }
+        }
+        Pattern::Or(alternatives) => {
+            for alternative in alternatives {
+                pattern_bindings(alternative, counts);
+            }
+        }
+        Pattern::Wildcard(_) | Pattern::Literal(_) => (),
+    }
+}
+
+// Counts every place in `node` that gives a name a value, a declaration, an assignment,
+// a parameter, a loop or catch variable, a pattern binding or an import
+fn bindings(node: &Node, counts: &mut HashMap<String, usize>) {
+    let mut bind = |name: &Token| *counts.entry(name.value.into_string()).or_insert(0) += 1;
+    match node {
+        Node::VarAssignNode { name, .. } | Node::VarReassignNode { name, .. } => bind(name),
+        Node::FunDef {
+            name,
+            arg_tokens,
+            rest,
+            ..
+        } => {
+            for name in name.iter().chain(arg_tokens).chain(rest) {
+                bind(name);
+            }
+        }
+        Node::ForInNode { var_name_token, .. } | Node::ForNode { var_name_token, .. } => {
+            bind(var_name_token)
+        }
+        Node::TryNode {
+            catch_var: Some(name),
+            ..
+        } => bind(name),
+        Node::ImportNode {
+            names, namespace, ..
+        } => {
+            for name in names.iter().map(|(_, local)| local).chain(namespace) {
+                bind(name);
+            }
+        }
+        Node::ClassDefNode { name, .. } | Node::EnumDefNode { name, .. } => bind(name),
+        Node::CompoundAssignNode { target, .. } => {
+            if let Node::VarAccessNode { token } = &**target {
+                bind(token);
+            }
+        }
+        Node::MatchNode { arms, .. } => {
+            for (pattern, _, _) in arms {
+                pattern_bindings(pattern, counts);
+            }
+        }
+        Node::DestructureNode { pattern, .. } => pattern_bindings(pattern, counts),
+        _ => (),
+    }
+
+    let children: Vec<&Node> = match node {
+        Node::WhileNode {
+            condition_node,
+            body_node,
+            ..
+        } => vec![condition_node, body_node],
+        Node::VarReassignNode { value, .. }
+        | Node::VarAssignNode { value, .. }
+        | Node::ThrowNode { value, .. }
+        | Node::DestructureNode { value, .. } => vec![value],
+        Node::UnaryNode { node, .. }
+        | Node::NullSafeNode { node }
+        | Node::ExportNode { node, .. } => vec![node],
+        Node::OptionalChainNode { chain } => vec![chain],
+        Node::DeleteNode { target, .. } => vec![target],
+        Node::IfNode { cases, else_case } => cases
+            .iter()
+            .flat_map(|(condition, body)| vec![condition, body])
+            .chain(else_case.iter())
+            .collect(),
+        Node::FunDef {
+            body_node,
+            defaults,
+            ..
+        } => defaults.iter().flatten().chain([&**body_node]).collect(),
+        Node::ForInNode {
+            iterable,
+            body_node,
+            ..
+        } => vec![iterable, body_node],
+        Node::ForNode {
+            start_value,
+            end_value,
+            body_node,
+            step_value_node,
+            ..
+        } => vec![start_value, end_value, step_value_node, body_node],
+        Node::CallNode {
+            node_to_call,
+            args,
+            named,
+        } => args
+            .iter()
+            .chain(named.iter().map(|(_, arg)| arg))
+            .chain([&**node_to_call])
+            .collect(),
+        Node::ClassInitNode {
+            constructor_params,
+            named,
+            ..
+        } => constructor_params
+            .iter()
+            .chain(named.iter().map(|(_, arg)| arg))
+            .collect(),
+        Node::MapNode { entries } => entries.iter().flat_map(|(k, v)| vec![k, v]).collect(),
+        Node::BinOpNode { left, right, .. } => vec![left, right],
+        Node::ArrayNode { element_nodes } => element_nodes.iter().collect(),
+        Node::Statements { statements } => statements.iter().collect(),
+        Node::ArrayAcess { array, index } => vec![array, index],
+        Node::ArrayIndexEdit {
+            array,
+            index,
+            new_val,
+        } => vec![array, index, new_val],
+        Node::ReturnNode { value } => value.iter().collect(),
+        Node::TryNode {
+            try_body,
+            catch_body,
+            finally_body,
+            ..
+        } => once(&**try_body)
+            .chain(catch_body.iter())
+            .chain(finally_body.iter())
+            .collect(),
+        Node::MatchNode { value, arms } => once(&**value)
+            .chain(
+                arms.iter()
+                    .flat_map(|(_, guard, body)| guard.iter().chain([body])),
+            )
+            .collect(),
+        Node::ObjectDefNode { properties } => properties.iter().map(|(_, v)| v).collect(),
+        Node::ObjectPropAccess { object, .. } => vec![object],
+        Node::ObjectPropEdit {
+            object, new_val, ..
+        } => vec![object, new_val],
+        Node::CompoundAssignNode { target, value, .. } => vec![target, value],
+        Node::ClassDefNode {
+            constructor,
+            properties,
+            methods,
+            statics,
+            getters,
+            setters,
+            ..
+        } => constructor
+            .iter()
+            .chain(
+                properties
+                    .iter()
+                    .chain(methods)
+                    .chain(statics)
+                    .chain(getters)
+                    .chain(setters)
+                    .map(|(_, member)| member),
+            )
+            .collect(),
+        _ => vec![],
+    };
+    for child in children {
+        bindings(child, counts);
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Module {
+    id: u16,
+    exports: HashMap<u16, Option<Params>>,
+}
+
 #[derive(Debug, Clone)]
 pub struct ByteCodeGen {
     pub bytecode: ByteCode,
     pub variables: HashMap<String, u16>,
+    loops: Vec<Loop>,
+    trys: Vec<TryFrame>,
+    scope_depth: usize,
+    signatures: HashMap<u16, Params>,
+    // names given a value more than once in the module being compiled, a call to one of
+    // them might not reach the function it was declared as so its arity isn't checked
+    rebound: Option<Rc<HashSet<String>>>,
+    modules: HashMap<String, Module>,
+    exports: Option<HashMap<u16, Option<Params>>>,
+    imports: Vec<u16>,
+    // how many hidden slots (`@iter`, `@match`, `@error`) are live, counting the ones
+    // of enclosing functions too as clear() keeps it, so a function body never reuses
+    // the name of a slot in the scope it captures
+    hidden: usize,
+    enums: HashMap<u16, Vec<(u16, usize)>>,
+    chains: Vec<Chain>,
+    open_calls: Vec<usize>,
+    warnings: Rc<RefCell<Vec<Error>>>,
 }
 
 impl ByteCodeGen {
@@ -108,6 +437,19 @@ impl ByteCodeGen {
         Self {
             bytecode: ByteCode::new(),
             variables,
+            loops: vec![],
+            trys: vec![],
+            scope_depth: 0,
+            signatures: HashMap::new(),
+            rebound: None,
+            modules: HashMap::new(),
+            exports: Some(HashMap::new()),
+            imports: vec![],
+            hidden: 0,
+            enums: HashMap::new(),
+            chains: vec![],
+            open_calls: vec![],
+            warnings: Rc::new(RefCell::new(vec![])),
         }
     }
 
@@ -128,21 +470,51 @@ impl ByteCodeGen {
     }
 
     fn add_instruction(&mut self, op: OpCode) -> u16 {
+        match op {
+            OpCode::OpBlockStart => self.scope_depth += 1,
+            OpCode::OpBlockEnd => self.scope_depth -= 1,
+            _ => (),
+        }
         let pos = self.bytecode.instructions.len() as u16;
         self.bytecode.instructions.extend(op.make_op());
         pos
     }
 
-    pub fn compile_node(&mut self, node: Node) {
+    pub fn compile_node(&mut self, node: Node) -> Result<(), Error> {
+        if self.rebound.is_none() {
+            let mut counts = HashMap::new();
+            bindings(&node, &mut counts);
+            self.rebound = Some(Rc::new(
+                counts
+                    .into_iter()
+                    .filter(|(_, count)| *count > 1)
+                    .map(|(name, _)| name)
+                    .collect(),
+            ));
+            let res = self.compile_node(node);
+            self.rebound = None;
+            return res;
+        }
         match node {
+            // Every node leaves exactly one value on the stack, statements that don't have
+            // one of their own leave null. The value of a body is that of its last statement
             Node::Statements { statements } => {
-                for statement in statements {
-                    self.compile_node(statement);
-                    self.add_instruction(OpCode::OpPop);
+                if statements.is_empty() {
+                    self.push_null();
+                }
+                let len = statements.len();
+                for (i, statement) in statements.into_iter().enumerate() {
+                    self.compile_node(statement)?;
+                    if i + 1 < len {
+                        self.add_instruction(OpCode::OpPop);
+                    }
                 }
             }
             Node::NumberNode { token } => {
-                if token.r#type == Tokens::Int {
+                if let DynType::BigInt(i) = token.value {
+                    let idx = self.add_constant(Constants::BigInt(i));
+                    self.add_instruction(OpCode::OpConstant(idx));
+                } else if token.r#type == Tokens::Int {
                     let idx = self.add_constant(Constants::Int(token.value.into_int()));
                     self.add_instruction(OpCode::OpConstant(idx));
                 } else {
@@ -162,41 +534,123 @@ impl ByteCodeGen {
                 let idx = self.add_constant(Constants::Boolean(token.value.into_boolean()));
                 self.add_instruction(OpCode::OpConstant(idx));
             }
+            Node::NullNode { .. } => {
+                let idx = self.add_constant(Constants::Null);
+                self.add_instruction(OpCode::OpConstant(idx));
+            }
+            Node::MapNode { entries } => {
+                let len = entries.len() as u16;
+                for (key, val) in entries {
+                    self.compile_node(key)?;
+                    self.compile_node(val)?;
+                }
+                self.add_instruction(OpCode::OpMap(len));
+            }
+            Node::DeleteNode { target, .. } => match *target {
+                Node::ObjectPropAccess { object, property } => {
+                    self.compile_node(*object)?;
+                    let name = Constants::String(property.value.into_string());
+                    let idx = self.add_constant(name);
+                    self.add_instruction(OpCode::OpConstant(idx));
+                    self.add_instruction(OpCode::OpDelete);
+                }
+                Node::ArrayAcess { array, index } => {
+                    self.compile_node(*array)?;
+                    self.compile_node(*index)?;
+                    self.add_instruction(OpCode::OpDelete);
+                }
+                _ => unreachable!(),
+            },
+            Node::OptionalChainNode { chain } => {
+                self.chains.push(Chain {
+                    calls: self.open_calls.len(),
+                    skips: vec![],
+                });
+                self.compile_node(*chain)?;
+                let chain = self.chains.pop().unwrap();
+                let mut ends = vec![];
+                let mut cleanups = vec![];
+                for (idx, calls) in chain.skips {
+                    if calls.is_empty() {
+                        ends.push(idx);
+                    } else {
+                        cleanups.push((idx, calls));
+                    }
+                }
+                if !cleanups.is_empty() {
+                    ends.push(self.add_instruction(OpCode::OpJump(0)));
+                }
+                // the calls have ended their blocks by now, but not where the skips come from
+                let scope_depth = self.scope_depth;
+                for (idx, calls) in cleanups {
+                    self.scope_depth = scope_depth + calls.len();
+                    self.patch_jump(idx, None);
+                    self.add_instruction(OpCode::OpPop);
+                    for pushed in calls {
+                        for _ in 0..pushed {
+                            self.add_instruction(OpCode::OpPop);
+                        }
+                        self.add_instruction(OpCode::OpBlockEnd);
+                    }
+                    let null = self.add_constant(Constants::Null);
+                    self.add_instruction(OpCode::OpConstant(null));
+                    ends.push(self.add_instruction(OpCode::OpJump(0)));
+                }
+                for end in ends {
+                    self.patch_jump(end, None);
+                }
+            }
+            Node::NullSafeNode { node } => {
+                self.compile_node(*node)?;
+                let idx = self.add_instruction(OpCode::OpJumpIfNull(0));
+                if let Some(chain) = self.chains.last_mut() {
+                    let calls = self.open_calls[chain.calls..].to_vec();
+                    chain.skips.push((idx, calls));
+                }
+            }
             Node::BinOpNode {
                 left,
                 right,
                 op_token,
             } => {
-                self.compile_node(*left);
-                self.compile_node(*right);
-
-                match op_token.r#type {
-                    Tokens::Plus => self.add_instruction(OpCode::OpAdd),
-                    Tokens::Minus => self.add_instruction(OpCode::OpSubtract),
-                    Tokens::Multiply => self.add_instruction(OpCode::OpMultiply),
-                    Tokens::Divide => self.add_instruction(OpCode::OpDivide),
-                    Tokens::Power => self.add_instruction(OpCode::OpPower),
-                    Tokens::DoubleEquals => self.add_instruction(OpCode::OpEquals),
-                    Tokens::NotEquals => self.add_instruction(OpCode::OpNotEquals),
-                    Tokens::GreaterThan => self.add_instruction(OpCode::OpGreaterThan),
-                    Tokens::GreaterThanEquals => self.add_instruction(OpCode::OpGreaterThanEquals),
-                    Tokens::LessThan => self.add_instruction(OpCode::OpLessThan),
-                    Tokens::LessThanEquals => self.add_instruction(OpCode::OpLessThanEquals),
-                    _ => 0,
-                };
-
-                if op_token.matches(Tokens::Keyword, DynType::String("and".to_string())) {
-                    self.add_instruction(OpCode::OpAnd);
-                } else if op_token.matches(Tokens::Keyword, DynType::String("or".to_string())) {
-                    self.add_instruction(OpCode::OpOr);
+                let and = op_token.matches(Tokens::Keyword, DynType::String("and".to_string()));
+                let or = op_token.matches(Tokens::Keyword, DynType::String("or".to_string()));
+                self.compile_node(*left)?;
+                if op_token.r#type == Tokens::DoubleQuestion {
+                    let idx = self.add_instruction(OpCode::OpJumpIfNull(0));
+                    let end = self.add_instruction(OpCode::OpJump(0));
+                    self.patch_jump(idx, None);
+                    self.add_instruction(OpCode::OpPop);
+                    self.compile_node(*right)?;
+                    self.patch_jump(end, None);
+                } else if and || or {
+                    // the right side is skipped once the left one decides the result, which
+                    // is then the value of the whole expression
+                    self.add_instruction(OpCode::OpDup);
+                    let idx = self.add_instruction(OpCode::OpJumpIfFalse(0));
+                    let mut end = None;
+                    if or {
+                        end = Some(self.add_instruction(OpCode::OpJump(0)));
+                        self.patch_jump_if_false(idx, None);
+                    }
+                    self.add_instruction(OpCode::OpPop);
+                    self.compile_node(*right)?;
+                    match end {
+                        Some(end) => self.patch_jump(end, None),
+                        None => self.patch_jump_if_false(idx, None),
+                    }
+                } else {
+                    self.compile_node(*right)?;
+                    self.compile_binary_op(&op_token);
                 }
             }
             Node::UnaryNode { node, op_token } => {
-                self.compile_node(*node);
+                self.compile_node(*node)?;
 
                 match op_token.r#type {
                     Tokens::Plus => self.add_instruction(OpCode::OpPlus),
                     Tokens::Minus => self.add_instruction(OpCode::OpMinus),
+                    Tokens::BitNot => self.add_instruction(OpCode::OpBitNot),
                     _ => 0,
                 };
 
@@ -209,7 +663,8 @@ impl ByteCodeGen {
                 value,
                 reassignable,
             } => {
-                self.compile_node(*value);
+                self.compile_node(*value)?;
+                self.add_instruction(OpCode::OpDup);
                 let idx = self.add_constant(Constants::Boolean(reassignable));
                 self.add_instruction(OpCode::OpConstant(idx));
                 let id = self.variable(name.value.into_string());
@@ -220,7 +675,8 @@ impl ByteCodeGen {
                 self.add_instruction(OpCode::OpVarAccess(id));
             }
             Node::VarReassignNode { name, value, .. } => {
-                self.compile_node(*value);
+                self.compile_node(*value)?;
+                self.add_instruction(OpCode::OpDup);
                 let id = self.variable(name.value.into_string());
                 self.add_instruction(OpCode::OpVarReassign(id));
             }
@@ -228,10 +684,10 @@ impl ByteCodeGen {
                 let mut jumps = vec![];
 
                 for (expr, body) in cases {
-                    self.compile_node(expr.clone());
+                    self.compile_node(expr.clone())?;
                     let idx = self.add_instruction(OpCode::OpJumpIfFalse(0));
                     self.add_instruction(OpCode::OpBlockStart);
-                    self.compile_node(body.clone());
+                    self.compile_node(body.clone())?;
                     self.add_instruction(OpCode::OpBlockEnd);
                     let idx_1 = self.add_instruction(OpCode::OpJump(0));
                     jumps.push(idx_1);
@@ -240,8 +696,10 @@ impl ByteCodeGen {
 
                 if else_case.is_some() {
                     self.add_instruction(OpCode::OpBlockStart);
-                    self.compile_node(else_case.unwrap());
+                    self.compile_node(else_case.unwrap())?;
                     self.add_instruction(OpCode::OpBlockEnd);
+                } else {
+                    self.push_null();
                 }
 
                 for jump in jumps {
@@ -254,93 +712,171 @@ impl ByteCodeGen {
                 step_value_node,
                 end_value,
                 body_node,
+                label,
             } => {
-                self.compile_node(*start_value);
+                self.add_instruction(OpCode::OpBlockStart);
+                self.compile_node(*start_value)?;
                 let idx = self.add_constant(Constants::Boolean(true));
                 self.add_instruction(OpCode::OpConstant(idx));
-                let id_1 = self.variable(var_name_token.value.into_string());
-                self.add_instruction(OpCode::OpVarAssign(id_1));
+                let id = self.variable(var_name_token.value.into_string());
+                self.add_instruction(OpCode::OpVarAssign(id));
 
-                let init = self.bytecode.instructions.len();
+                // the end and the step are only evaluated once, before the loop
+                let hidden = [("@end", *end_value), ("@step", *step_value_node)];
+                let mut ids = vec![];
+                for (name, node) in hidden {
+                    self.compile_node(node)?;
+                    let idx = self.add_constant(Constants::Boolean(false));
+                    self.add_instruction(OpCode::OpConstant(idx));
+                    let hidden_id = self.variable(format!("{}{}", name, self.hidden));
+                    self.add_instruction(OpCode::OpVarAssign(hidden_id));
+                    ids.push(hidden_id);
+                }
+                let (end_id, step_id) = (ids[0], ids[1]);
+                self.hidden += 1;
 
-                let id_2 = self.variable(var_name_token.value.into_string());
-                self.add_instruction(OpCode::OpVarAccess(id_2));
-                self.compile_node(*end_value);
+                // a step of 0 would never reach the end, so the loop doesn't run at all
+                self.add_instruction(OpCode::OpVarAccess(step_id));
+                let zero = self.add_constant(Constants::Int(0));
+                self.add_instruction(OpCode::OpConstant(zero));
                 self.add_instruction(OpCode::OpNotEquals);
+                let zero_step = self.add_instruction(OpCode::OpJumpIfFalse(1));
 
-                let idx_3 = self.add_instruction(OpCode::OpJumpIfFalse(1));
+                let init = self.bytecode.instructions.len();
 
-                let id_3 = self.variable(var_name_token.value.into_string());
-                self.add_instruction(OpCode::OpVarAccess(id_3));
-                self.compile_node(*step_value_node);
+                self.add_instruction(OpCode::OpVarAccess(id));
+                self.add_instruction(OpCode::OpVarAccess(step_id));
                 self.add_instruction(OpCode::OpAdd);
+                self.add_instruction(OpCode::OpVarAccess(end_id));
+                self.add_instruction(OpCode::OpSubtract);
+                self.add_instruction(OpCode::OpVarAccess(step_id));
+                self.add_instruction(OpCode::OpMultiply);
+                self.add_instruction(OpCode::OpConstant(zero));
+                self.add_instruction(OpCode::OpLessThanEquals);
 
-                let id_4 = self.variable(var_name_token.value.into_string());
-                self.add_instruction(OpCode::OpVarReassign(id_4));
+                let idx_3 = self.add_instruction(OpCode::OpJumpIfFalse(1));
+
+                self.add_instruction(OpCode::OpVarAccess(id));
+                self.add_instruction(OpCode::OpVarAccess(step_id));
+                self.add_instruction(OpCode::OpAdd);
+                self.add_instruction(OpCode::OpVarReassign(id));
 
+                self.start_loop(label, init as u16);
                 self.add_instruction(OpCode::OpBlockStart);
-                self.compile_node(*body_node.clone());
+                self.compile_node(*body_node.clone())?;
+                self.add_instruction(OpCode::OpPop);
                 self.add_instruction(OpCode::OpBlockEnd);
                 let jmp = self.add_instruction(OpCode::OpJump(0));
                 self.patch_jump_if_false(idx_3, None);
+                self.patch_jump_if_false(zero_step, None);
                 self.patch_jump(jmp, Some(init as u16));
+                self.end_loop();
+                self.hidden -= 1;
+                self.add_instruction(OpCode::OpBlockEnd);
+                self.push_null();
+            }
+            Node::ForInNode {
+                var_name_token,
+                iterable,
+                body_node,
+                label,
+            } => {
+                self.add_instruction(OpCode::OpBlockStart);
+                self.compile_node(*iterable)?;
+                self.add_instruction(OpCode::OpIterStart);
+                let idx = self.add_constant(Constants::Boolean(false));
+                self.add_instruction(OpCode::OpConstant(idx));
+                let iter_id = self.variable(format!("@iter{}", self.hidden));
+                self.hidden += 1;
+                self.add_instruction(OpCode::OpVarAssign(iter_id));
+
+                let init = self.add_instruction(OpCode::OpVarAccess(iter_id));
+                let next = self.add_instruction(OpCode::OpIterNext(0));
+
+                self.start_loop(label, init);
+                self.add_instruction(OpCode::OpBlockStart);
+                let idx_2 = self.add_constant(Constants::Boolean(true));
+                self.add_instruction(OpCode::OpConstant(idx_2));
+                let id = self.variable(var_name_token.value.into_string());
+                self.add_instruction(OpCode::OpVarAssign(id));
+                self.compile_node(*body_node)?;
+                self.add_instruction(OpCode::OpPop);
+                self.add_instruction(OpCode::OpBlockEnd);
+                let jmp = self.add_instruction(OpCode::OpJump(0));
+                self.patch_jump(next, None);
+                self.patch_jump(jmp, Some(init));
+                self.end_loop();
+                self.hidden -= 1;
+                self.add_instruction(OpCode::OpBlockEnd);
+                self.push_null();
             }
             Node::WhileNode {
                 condition_node,
                 body_node,
+                label,
             } => {
                 let init = self.bytecode.instructions.len();
-                self.compile_node(*condition_node.clone());
+                self.compile_node(*condition_node.clone())?;
                 let idx = self.add_instruction(OpCode::OpJumpIfFalse(0));
+                self.start_loop(label, init as u16);
                 self.add_instruction(OpCode::OpBlockStart);
-                self.compile_node(*body_node.clone());
+                self.compile_node(*body_node.clone())?;
+                self.add_instruction(OpCode::OpPop);
                 self.add_instruction(OpCode::OpBlockEnd);
                 let jmp = self.add_instruction(OpCode::OpJump(0));
                 self.patch_jump_if_false(idx, None);
                 self.patch_jump(jmp, Some(init as u16));
+                self.end_loop();
+                self.push_null();
+            }
+            Node::BreakNode { token, label } => {
+                let idx = self.find_loop(&token, &label)?;
+                self.unwind(Some(self.loops[idx].scope_depth), self.loops[idx].try_depth)?;
+                let jmp = self.add_instruction(OpCode::OpJump(0));
+                self.loops[idx].breaks.push(jmp);
+            }
+            Node::ContinueNode { token, label } => {
+                let idx = self.find_loop(&token, &label)?;
+                self.unwind(Some(self.loops[idx].scope_depth), self.loops[idx].try_depth)?;
+                self.add_instruction(OpCode::OpJump(self.loops[idx].start));
             }
             Node::FunDef {
                 name,
                 body_node,
                 arg_tokens,
+                defaults,
+                rest,
             } => {
-                let mut func_byte = self.clear();
-                let mut args: Vec<u16> = vec![];
-                for arg in arg_tokens {
-                    let id = func_byte.variable(arg.value.into_string());
-                    args.push(id);
-                }
-                func_byte.compile_node(*body_node);
-                self.variables = func_byte.variables;
-                let idx = self.add_constant(Constants::Function(args, func_byte.bytecode));
+                let name_id = name.as_ref().map(|n| self.variable(n.value.into_string()));
+                let params = self.compile_params(arg_tokens, defaults, rest)?;
+                if let (Some(name_id), Some(name)) = (name_id, &name) {
+                    if !self.is_rebound(name) {
+                        self.signatures.insert(name_id, params.clone());
+                    }
+                }
+                let body = self.compile_body(*body_node)?;
+                let idx = self.add_constant(Constants::Function(name_id, params, body));
                 self.add_instruction(OpCode::OpConstant(idx));
                 if name.is_some() {
+                    self.add_instruction(OpCode::OpDup);
                     let idx_ = self.add_constant(Constants::Boolean(false));
                     self.add_instruction(OpCode::OpConstant(idx_));
                     let id = self.variable(name.unwrap().value.into_string());
                     self.add_instruction(OpCode::OpVarAssign(id));
                 }
             }
-            Node::CallNode { node_to_call, args } => {
-                let mut array = vec![];
-                self.add_instruction(OpCode::OpBlockStart);
-                for arg in &args {
-                    let mut array_btc = self.clear();
-                    array_btc.compile_node(arg.clone());
-                    array.push(array_btc.bytecode);
-                    self.variables = array_btc.variables;
-                }
-                let idx = self.add_constant(Constants::RawArray(array));
-                self.add_instruction(OpCode::OpConstant(idx));
-                self.compile_node(*node_to_call);
-                self.add_instruction(OpCode::OpBlockEnd);
-                self.add_instruction(OpCode::OpCall);
+            Node::CallNode {
+                node_to_call,
+                args,
+                named,
+            } => {
+                self.compile_call(*node_to_call, args, named)?;
             }
             Node::ArrayNode { element_nodes } => {
                 let mut array = vec![];
                 for element in element_nodes {
                     let mut array_btc = self.clear();
-                    array_btc.compile_node(element);
+                    array_btc.compile_node(element)?;
                     array.push(array_btc.bytecode);
                     self.variables = array_btc.variables;
                 }
@@ -348,16 +884,26 @@ impl ByteCodeGen {
                 self.add_instruction(OpCode::OpConstant(idx));
             }
             Node::ArrayAcess { array, index } => {
-                self.compile_node(*array);
-                self.compile_node(*index);
+                self.compile_node(*array)?;
+                self.compile_node(*index)?;
                 self.add_instruction(OpCode::OpIndexArray);
             }
+            Node::ArrayIndexEdit {
+                array,
+                index,
+                new_val,
+            } => {
+                self.compile_node(*array)?;
+                self.compile_node(*index)?;
+                self.compile_node(*new_val)?;
+                self.add_instruction(OpCode::OpIndexAssign);
+            }
             Node::ObjectDefNode { properties } => {
                 let mut compiled_properties = HashMap::new();
                 for (k, v) in &properties {
                     let id = self.variable(k.value.into_string());
                     let mut val_btc = self.clear();
-                    val_btc.compile_node(v.clone());
+                    val_btc.compile_node(v.clone())?;
                     compiled_properties.insert(id as usize, val_btc.bytecode);
                     self.variables = val_btc.variables.clone();
                 }
@@ -365,7 +911,7 @@ impl ByteCodeGen {
                 self.add_instruction(OpCode::OpConstant(idx));
             }
             Node::ObjectPropAccess { object, property } => {
-                self.compile_node(*object);
+                self.compile_node(*object)?;
                 let id = self.variable(property.value.into_string());
                 self.add_instruction(OpCode::OpPropertyAccess(id));
             }
@@ -374,57 +920,401 @@ impl ByteCodeGen {
                 new_val,
                 property,
             } => {
-                self.compile_node(*object);
-                self.compile_node(*new_val);
+                self.compile_node(*object)?;
+                self.compile_node(*new_val)?;
                 let id = self.variable(property.value.into_string());
                 self.add_instruction(OpCode::OpPropertyAssign(id));
             }
             Node::ReturnNode { value } => {
                 if value.is_some() {
-                    self.compile_node(value.unwrap());
+                    self.compile_node(value.unwrap())?;
+                } else {
+                    let idx = self.add_constant(Constants::Null);
+                    self.add_instruction(OpCode::OpConstant(idx));
+                }
+
+                if self.trys.iter().any(|frame| frame.finally.is_some()) {
+                    self.add_instruction(OpCode::OpBlockStart);
+                    let idx = self.add_constant(Constants::Boolean(false));
+                    self.add_instruction(OpCode::OpConstant(idx));
+                    let id = self.variable(String::from("@return"));
+                    self.add_instruction(OpCode::OpVarAssign(id));
+                    self.unwind(None, 0)?;
+                    self.add_instruction(OpCode::OpVarAccess(id));
+                    self.add_instruction(OpCode::OpReturn);
+                    self.scope_depth -= 1;
                 } else {
-                    self.add_constant(Constants::Null);
+                    self.add_instruction(OpCode::OpReturn);
+                }
+            }
+            Node::MatchNode { value, arms } => {
+                let subject = self.variable(format!("@match{}", self.hidden));
+                self.hidden += 1;
+                self.add_instruction(OpCode::OpBlockStart);
+                self.compile_node(*value)?;
+                let idx = self.add_constant(Constants::Boolean(false));
+                self.add_instruction(OpCode::OpConstant(idx));
+                self.add_instruction(OpCode::OpVarAssign(subject));
+
+                let mut ends = vec![];
+                for (i, (pattern, guard, body)) in arms.iter().enumerate() {
+                    if arms[..i]
+                        .iter()
+                        .any(|(p, g, _)| g.is_none() && covers(p, pattern))
+                    {
+                        let token = pattern.token();
+                        self.warnings.borrow_mut().push(Error::new(
+                            "Unreachable Pattern",
+                            token.pos_start,
+                            token.pos_end,
+                            "This arm is never reached, an earlier one matches the same values",
+                        ));
+                    }
+
+                    self.add_instruction(OpCode::OpBlockStart);
+                    let mut fails = vec![];
+                    let mut bindings = vec![];
+                    self.compile_pattern(pattern, subject, vec![], &mut fails, &mut bindings)?;
+                    for (name, path) in bindings {
+                        self.load(subject, &path);
+                        let idx = self.add_constant(Constants::Boolean(false));
+                        self.add_instruction(OpCode::OpConstant(idx));
+                        let id = self.variable(name.value.into_string());
+                        self.add_instruction(OpCode::OpVarAssign(id));
+                    }
+                    if let Some(guard) = guard {
+                        self.compile_node(guard.clone())?;
+                        fails.push(self.add_instruction(OpCode::OpJumpIfFalse(0)));
+                    }
+                    self.compile_node(body.clone())?;
+                    self.add_instruction(OpCode::OpBlockEnd);
+                    ends.push(self.add_instruction(OpCode::OpJump(0)));
+
+                    if !fails.is_empty() {
+                        for fail in fails {
+                            self.patch_jump_if_false(fail, None);
+                        }
+                        // A failed arm leaves its scope as well
+                        self.add_instruction(OpCode::OpBlockEnd);
+                        self.scope_depth += 1;
+                    }
                 }
+                let idx =
+                    self.add_constant(Constants::String(String::from("No arm matched the value")));
+                self.add_instruction(OpCode::OpConstant(idx));
+                self.add_instruction(OpCode::OpNoMatch);
 
-                self.add_instruction(OpCode::OpReturn);
+                for end in ends {
+                    self.patch_jump(end, None);
+                }
+                self.add_instruction(OpCode::OpBlockEnd);
+                self.hidden -= 1;
             }
+            Node::DestructureNode {
+                pattern,
+                value,
+                reassignable,
+            } => {
+                let subject = self.variable(String::from("@destructure"));
+                self.compile_node(*value)?;
+                self.add_instruction(OpCode::OpDup);
+                self.add_instruction(OpCode::OpBlockStart);
+                let idx = self.add_constant(Constants::Boolean(false));
+                self.add_instruction(OpCode::OpConstant(idx));
+                self.add_instruction(OpCode::OpVarAssign(subject));
+
+                let mut fails = vec![];
+                let mut bindings = vec![];
+                self.compile_pattern(&pattern, subject, vec![], &mut fails, &mut bindings)?;
+                // The values are kept on the stack so they are bound outside this block
+                for (_, path) in &bindings {
+                    self.load(subject, path);
+                }
+                self.add_instruction(OpCode::OpBlockEnd);
+                let ok = self.add_instruction(OpCode::OpJump(0));
+
+                for fail in fails {
+                    self.patch_jump_if_false(fail, None);
+                }
+                let idx = self.add_constant(Constants::String(String::from(
+                    "Value doesn't match the pattern",
+                )));
+                self.add_instruction(OpCode::OpConstant(idx));
+                self.add_instruction(OpCode::OpNoMatch);
+
+                self.patch_jump(ok, None);
+                for (name, _) in bindings.into_iter().rev() {
+                    let idx = self.add_constant(Constants::Boolean(reassignable));
+                    self.add_instruction(OpCode::OpConstant(idx));
+                    let id = self.variable(name.value.into_string());
+                    self.add_instruction(OpCode::OpVarAssign(id));
+                }
+            }
+            Node::ImportNode {
+                path,
+                names,
+                namespace,
+            } => {
+                if self.exports.is_none() || self.scope_depth != 0 {
+                    return Err(Error::new(
+                        "Import Error",
+                        path.pos_start,
+                        path.pos_end,
+                        "Imports are only allowed at the top level of a module",
+                    ));
+                }
+                let module = match self.modules.get(&path.value.into_string()) {
+                    Some(module) => module.clone(),
+                    None => {
+                        return Err(Error::new(
+                            "Import Error",
+                            path.pos_start,
+                            path.pos_end,
+                            "Module was not loaded",
+                        ))
+                    }
+                };
+                if !self.imports.contains(&module.id) {
+                    self.imports.push(module.id);
+                }
+
+                if let Some(namespace) = namespace {
+                    self.add_instruction(OpCode::OpVarAccess(module.id));
+                    let idx = self.add_constant(Constants::Boolean(false));
+                    self.add_instruction(OpCode::OpConstant(idx));
+                    let id = self.variable(namespace.value.into_string());
+                    self.add_instruction(OpCode::OpVarAssign(id));
+                }
+                for (name, local) in names {
+                    let id = self.variable(name.value.into_string());
+                    let signature = match module.exports.get(&id) {
+                        Some(signature) => signature.clone(),
+                        None => {
+                            return Err(Error::new(
+                                "Import Error",
+                                name.pos_start,
+                                name.pos_end,
+                                Box::leak(
+                                    format!(
+                                        "Module has no export named '{}'",
+                                        name.value.into_string()
+                                    )
+                                    .into_boxed_str(),
+                                ),
+                            ))
+                        }
+                    };
+                    self.add_instruction(OpCode::OpVarAccess(module.id));
+                    self.add_instruction(OpCode::OpPropertyAccess(id));
+                    let idx = self.add_constant(Constants::Boolean(false));
+                    self.add_instruction(OpCode::OpConstant(idx));
+                    let local_id = self.variable(local.value.into_string());
+                    self.add_instruction(OpCode::OpVarAssign(local_id));
+                    if let Some(signature) = signature {
+                        if !self.is_rebound(&local) {
+                            self.signatures.insert(local_id, signature);
+                        }
+                    }
+                }
+                self.push_null();
+            }
+            Node::ExportNode { token, node } => {
+                if self.exports.is_none() || self.scope_depth != 0 {
+                    return Err(Error::new(
+                        "Export Error",
+                        token.pos_start,
+                        token.pos_end,
+                        "Exports are only allowed at the top level of a module",
+                    ));
+                }
+                let name = match &*node {
+                    Node::VarAssignNode { name, .. }
+                    | Node::ClassDefNode { name, .. }
+                    | Node::EnumDefNode { name, .. } => name,
+                    Node::FunDef {
+                        name: Some(name), ..
+                    } => name,
+                    _ => panic!("Invalid node for export"),
+                };
+                let id = self.variable(name.value.into_string());
+                self.compile_node(*node)?;
+                let signature = self.signatures.get(&id).cloned();
+                self.exports.as_mut().unwrap().insert(id, signature);
+            }
+            Node::ThrowNode { value, .. } => {
+                self.compile_node(*value)?;
+                self.add_instruction(OpCode::OpThrow);
+            }
+            Node::TryNode {
+                try_body,
+                catch_var,
+                catch_body,
+                finally_body,
+            } => {
+                let finally_handler = if finally_body.is_some() {
+                    Some(self.start_try(*finally_body.clone()))
+                } else {
+                    None
+                };
+
+                match *catch_body {
+                    Some(catch_body) => {
+                        let handler = self.start_try(None);
+                        self.add_instruction(OpCode::OpBlockStart);
+                        self.compile_node(*try_body)?;
+                        self.add_instruction(OpCode::OpBlockEnd);
+                        self.end_try();
+                        let jmp = self.add_instruction(OpCode::OpJump(0));
+
+                        self.patch_jump(handler, None);
+                        self.add_instruction(OpCode::OpBlockStart);
+                        let idx = self.add_constant(Constants::Boolean(true));
+                        self.add_instruction(OpCode::OpConstant(idx));
+                        let id = self.variable(catch_var.unwrap().value.into_string());
+                        self.add_instruction(OpCode::OpVarAssign(id));
+                        self.compile_node(catch_body)?;
+                        self.add_instruction(OpCode::OpBlockEnd);
+                        self.patch_jump(jmp, None);
+                    }
+                    None => {
+                        self.add_instruction(OpCode::OpBlockStart);
+                        self.compile_node(*try_body)?;
+                        self.add_instruction(OpCode::OpBlockEnd);
+                    }
+                }
+
+                if let Some(handler) = finally_handler {
+                    let finally_body = finally_body.unwrap();
+                    self.end_try();
+                    self.add_instruction(OpCode::OpBlockStart);
+                    self.compile_node(finally_body.clone())?;
+                    self.add_instruction(OpCode::OpPop);
+                    self.add_instruction(OpCode::OpBlockEnd);
+                    let jmp = self.add_instruction(OpCode::OpJump(0));
+
+                    self.patch_jump(handler, None);
+                    self.add_instruction(OpCode::OpBlockStart);
+                    let idx = self.add_constant(Constants::Boolean(false));
+                    self.add_instruction(OpCode::OpConstant(idx));
+                    let id = self.variable(format!("@error{}", self.hidden));
+                    self.add_instruction(OpCode::OpVarAssign(id));
+                    self.hidden += 1;
+                    self.compile_node(finally_body)?;
+                    self.add_instruction(OpCode::OpPop);
+                    self.hidden -= 1;
+                    self.add_instruction(OpCode::OpVarAccess(id));
+                    self.add_instruction(OpCode::OpThrow);
+                    self.add_instruction(OpCode::OpBlockEnd);
+                    self.patch_jump(jmp, None);
+                }
+            }
+            Node::CompoundAssignNode {
+                target,
+                op_token,
+                value,
+            } => match *target {
+                Node::VarAccessNode { token } => {
+                    let id = self.variable(token.value.into_string());
+                    self.add_instruction(OpCode::OpVarAccess(id));
+                    self.compile_node(*value)?;
+                    self.compile_binary_op(&op_token);
+                    self.add_instruction(OpCode::OpDup);
+                    self.add_instruction(OpCode::OpVarReassign(id));
+                }
+                Node::ObjectPropAccess { object, property } => {
+                    self.compile_node(*object)?;
+                    self.add_instruction(OpCode::OpDup);
+                    let id = self.variable(property.value.into_string());
+                    self.add_instruction(OpCode::OpPropertyAccess(id));
+                    self.compile_node(*value)?;
+                    self.compile_binary_op(&op_token);
+                    self.add_instruction(OpCode::OpPropertyAssign(id));
+                }
+                Node::ArrayAcess { array, index } => {
+                    self.compile_node(*array)?;
+                    self.compile_node(*index)?;
+                    self.add_instruction(OpCode::OpDupTwo);
+                    self.add_instruction(OpCode::OpIndexArray);
+                    self.compile_node(*value)?;
+                    self.compile_binary_op(&op_token);
+                    self.add_instruction(OpCode::OpIndexAssign);
+                }
+                _ => panic!("Invalid target for compound assignment"),
+            },
             Node::ClassDefNode {
                 constructor,
                 methods,
                 name,
+                parent,
                 properties,
+                statics,
+                getters,
+                setters,
             } => {
                 let mut constr = None;
-                let mut props = HashMap::new();
-                let mut btc = self.clear();
-                if constructor.is_some() {
-                    let constr_ = constructor.unwrap();
-                    btc.compile_node(constr_.1);
-                    let mut tok = vec![];
-                    for t in constr_.0 {
-                        let id = self.variable(t.value.into_string());
-                        tok.push(id);
-                    }
-                    self.variables = btc.variables.clone();
-                    let body = btc.bytecode.clone();
-                    btc = btc.clear();
-                    constr = Some((tok, body));
+                if let Some(Node::FunDef {
+                    body_node,
+                    arg_tokens,
+                    defaults,
+                    rest,
+                    ..
+                }) = *constructor
+                {
+                    let params = self.compile_params(arg_tokens, defaults, rest)?;
+                    let body = self.compile_body(*body_node)?;
+                    constr = Some((params, body));
                 };
 
                 let mut prop_temp = properties.clone();
                 prop_temp.extend(methods);
 
-                for (name, body) in &prop_temp {
-                    btc = btc.clear();
-                    let id = btc.variable(name.value.into_string()) as usize;
-                    btc.compile_node(body.clone());
-                    self.variables = btc.variables.clone();
-                    props.insert(id, btc.bytecode.clone());
+                let template = ClassTemplate {
+                    name: self.variable(name.value.into_string()),
+                    constructor: constr,
+                    props: self.compile_members(&prop_temp)?,
+                    statics: self.compile_members(&statics)?,
+                    getters: self.compile_members(&getters)?,
+                    setters: self.compile_members(&setters)?,
+                };
+                let idx = self.add_constant(Constants::RawClass(template));
+                self.add_instruction(OpCode::OpConstant(idx));
+                if let Some(parent) = parent {
+                    let parent_id = self.variable(parent.value.into_string());
+                    self.add_instruction(OpCode::OpVarAccess(parent_id));
+                    self.add_instruction(OpCode::OpInherit);
+                }
+                let id = self.variable(name.value.into_string());
+                self.add_instruction(OpCode::OpDup);
+                let idx_2 = self.add_constant(Constants::Boolean(false));
+                self.add_instruction(OpCode::OpConstant(idx_2));
+                self.add_instruction(OpCode::OpVarAssign(id));
+            }
+            Node::EnumDefNode { name, variants } => {
+                let id = self.variable(name.value.into_string());
+                let mut template = EnumTemplate {
+                    name: id,
+                    variants: vec![],
+                };
+                for (variant, fields) in variants {
+                    let variant_id = self.variable(variant.value.into_string());
+                    let field_ids = fields
+                        .into_iter()
+                        .map(|f| self.variable(f.value.into_string()))
+                        .collect();
+                    template.variants.push((variant_id, field_ids));
                 }
+                self.enums.insert(
+                    id,
+                    template
+                        .variants
+                        .iter()
+                        .map(|(v, fields)| (*v, fields.len()))
+                        .collect(),
+                );
 
-                let idx = self.add_constant(Constants::RawClass(constr, props));
+                let idx = self.add_constant(Constants::Enum(template));
                 self.add_instruction(OpCode::OpConstant(idx));
-                let id = self.variable(name.value.into_string());
+                self.add_instruction(OpCode::OpDup);
                 let idx_2 = self.add_constant(Constants::Boolean(false));
                 self.add_instruction(OpCode::OpConstant(idx_2));
                 self.add_instruction(OpCode::OpVarAssign(id));
@@ -432,23 +1322,501 @@ impl ByteCodeGen {
             Node::ClassInitNode {
                 name,
                 constructor_params,
+                named,
             } => {
-                self.add_instruction(OpCode::OpBlockStart);
-                let mut array = vec![];
-                for arg in &constructor_params {
-                    let mut array_btc = self.clear();
-                    array_btc.compile_node(arg.clone());
-                    array.push(array_btc.bytecode);
-                    self.variables = array_btc.variables;
+                self.compile_call(
+                    Node::VarAccessNode { token: name },
+                    constructor_params,
+                    named,
+                )?;
+            }
+        }
+
+        Ok(())
+    }
+
+    // Compiles a module into its own namespace, the code to load it is added here and
+    // `import` statements compiled afterwards can refer to it by `path`
+    pub fn compile_module(&mut self, path: String, node: Node) -> Result<(), Error> {
+        let mut module = self.clear();
+        module.exports = Some(HashMap::new());
+        module.signatures = HashMap::new();
+        module.rebound = None;
+        module.compile_node(node)?;
+        self.variables = module.variables;
+
+        let exports = module.exports.unwrap();
+        let id = self.variable(format!("@module{}", self.modules.len()));
+        let idx = self.add_constant(Constants::Module(
+            module.imports,
+            exports.keys().cloned().collect(),
+            module.bytecode,
+        ));
+        self.add_instruction(OpCode::OpConstant(idx));
+        let idx = self.add_constant(Constants::Boolean(false));
+        self.add_instruction(OpCode::OpConstant(idx));
+        self.add_instruction(OpCode::OpVarAssign(id));
+        self.modules.insert(path, Module { id, exports });
+        Ok(())
+    }
+
+    fn push_null(&mut self) {
+        let idx = self.add_constant(Constants::Null);
+        self.add_instruction(OpCode::OpConstant(idx));
+    }
+
+    fn is_rebound(&self, name: &Token) -> bool {
+        self.rebound
+            .as_ref()
+            .is_none_or(|rebound| rebound.contains(&name.value.into_string()))
+    }
+
+    pub fn warnings(&self) -> Vec<Error> {
+        self.warnings.borrow().clone()
+    }
+
+    // Pushes the part of `subject` found by following `path`
+    fn load(&mut self, subject: u16, path: &[Step]) {
+        self.add_instruction(OpCode::OpVarAccess(subject));
+        for step in path {
+            match step {
+                Step::Index(i) => {
+                    let idx = self.add_constant(Constants::Int(*i as i128));
+                    self.add_instruction(OpCode::OpConstant(idx));
+                    self.add_instruction(OpCode::OpIndexArray);
                 }
-                let idx = self.add_constant(Constants::RawArray(array));
+                Step::Property(id) => {
+                    self.add_instruction(OpCode::OpPropertyAccess(*id));
+                }
+                Step::Slice(start) => {
+                    self.add_instruction(OpCode::OpArraySlice(*start));
+                }
+            }
+        }
+    }
+
+    // Emits the checks of `pattern`, adding a jump to `fails` for each of them, and collects
+    // the names it binds with the path to their values so the caller decides where they go
+    fn compile_pattern(
+        &mut self,
+        pattern: &Pattern,
+        subject: u16,
+        path: Vec<Step>,
+        fails: &mut Vec<u16>,
+        bindings: &mut Vec<(Token, Vec<Step>)>,
+    ) -> Result<(), Error> {
+        match pattern {
+            Pattern::Wildcard(_) => (),
+            Pattern::Binding(name) => bindings.push((name.clone(), path)),
+            Pattern::Literal(token) => {
+                self.load(subject, &path);
+                self.compile_node(match token.r#type {
+                    Tokens::String => Node::StringNode {
+                        token: token.clone(),
+                    },
+                    Tokens::Char => Node::CharNode {
+                        token: token.clone(),
+                    },
+                    Tokens::Boolean => Node::BooleanNode {
+                        token: token.clone(),
+                    },
+                    Tokens::Keyword => Node::NullNode {
+                        token: token.clone(),
+                    },
+                    _ => Node::NumberNode {
+                        token: token.clone(),
+                    },
+                })?;
+                self.add_instruction(OpCode::OpMatchEquals);
+                fails.push(self.add_instruction(OpCode::OpJumpIfFalse(0)));
+            }
+            Pattern::Array(_, elements, rest) => {
+                self.load(subject, &path);
+                let len = elements.len() as u16;
+                self.add_instruction(if rest.is_some() {
+                    OpCode::OpMatchArrayMin(len)
+                } else {
+                    OpCode::OpMatchArray(len)
+                });
+                fails.push(self.add_instruction(OpCode::OpJumpIfFalse(0)));
+                for (i, element) in elements.iter().enumerate() {
+                    let mut element_path = path.clone();
+                    element_path.push(Step::Index(i));
+                    self.compile_pattern(element, subject, element_path, fails, bindings)?;
+                }
+                if let Some(rest) = rest {
+                    if rest.value.into_string() != "_" {
+                        let mut rest_path = path;
+                        rest_path.push(Step::Slice(len));
+                        bindings.push((rest.clone(), rest_path));
+                    }
+                }
+            }
+            Pattern::Object(_, entries) => {
+                for (key, value) in entries {
+                    let id = self.variable(key.value.into_string());
+                    self.load(subject, &path);
+                    self.add_instruction(OpCode::OpHasProperty(id));
+                    fails.push(self.add_instruction(OpCode::OpJumpIfFalse(0)));
+                    let mut property_path = path.clone();
+                    property_path.push(Step::Property(id));
+                    self.compile_pattern(value, subject, property_path, fails, bindings)?;
+                }
+            }
+            Pattern::Variant(enum_name, variant, fields) => {
+                let enum_id = self.variable(enum_name.value.into_string());
+                let variant_id = self.variable(variant.value.into_string());
+                // Enums declared in this file are checked here, imported ones only at runtime
+                if let Some(variants) = self.enums.get(&enum_id) {
+                    let arity = match variants.iter().find(|(v, _)| *v == variant_id) {
+                        Some((_, arity)) => *arity,
+                        None => {
+                            return Err(Error::new(
+                                "Invalid Pattern",
+                                variant.pos_start,
+                                variant.pos_end,
+                                Box::leak(
+                                    format!(
+                                        "Enum '{}' has no variant '{}'",
+                                        enum_name.value.into_string(),
+                                        variant.value.into_string()
+                                    )
+                                    .into_boxed_str(),
+                                ),
+                            ))
+                        }
+                    };
+                    if arity != fields.len() {
+                        return Err(Error::new(
+                            "Invalid Pattern",
+                            variant.pos_start,
+                            variant.pos_end,
+                            Box::leak(
+                                format!("Expected {} fields but found {}", arity, fields.len())
+                                    .into_boxed_str(),
+                            ),
+                        ));
+                    }
+                }
+
+                self.load(subject, &path);
+                self.add_instruction(OpCode::OpVarAccess(enum_id));
+                self.add_instruction(OpCode::OpMatchVariant(variant_id));
+                fails.push(self.add_instruction(OpCode::OpJumpIfFalse(0)));
+                for (i, field) in fields.iter().enumerate() {
+                    let mut field_path = path.clone();
+                    field_path.push(Step::Index(i));
+                    self.compile_pattern(field, subject, field_path, fails, bindings)?;
+                }
+            }
+            Pattern::Or(alternatives) => {
+                let mut oks = vec![];
+                for (i, alternative) in alternatives.iter().enumerate() {
+                    let mut alternative_fails = vec![];
+                    let mut alternative_bindings = vec![];
+                    self.compile_pattern(
+                        alternative,
+                        subject,
+                        path.clone(),
+                        &mut alternative_fails,
+                        &mut alternative_bindings,
+                    )?;
+                    if let Some((name, _)) = alternative_bindings.first() {
+                        return Err(Error::new(
+                            "Invalid Pattern",
+                            name.pos_start,
+                            name.pos_end,
+                            "Alternatives can't bind names",
+                        ));
+                    }
+                    if i == alternatives.len() - 1 {
+                        fails.extend(alternative_fails);
+                    } else {
+                        oks.push(self.add_instruction(OpCode::OpJump(0)));
+                        for fail in alternative_fails {
+                            self.patch_jump_if_false(fail, None);
+                        }
+                    }
+                }
+                for ok in oks {
+                    self.patch_jump(ok, None);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn compile_params(
+        &mut self,
+        arg_tokens: Vec<Token>,
+        defaults: Vec<Option<Node>>,
+        rest: Option<Token>,
+    ) -> Result<Params, Error> {
+        let mut names = vec![];
+        for arg in arg_tokens {
+            names.push(self.variable(arg.value.into_string()));
+        }
+        let mut compiled = vec![];
+        for default in defaults {
+            compiled.push(match default {
+                Some(default) => {
+                    let mut btc = self.clear();
+                    btc.compile_node(default)?;
+                    self.variables = btc.variables;
+                    Some(btc.bytecode)
+                }
+                None => None,
+            });
+        }
+        Ok(Params {
+            names,
+            defaults: compiled,
+            rest: rest.map(|r| self.variable(r.value.into_string())),
+        })
+    }
+
+    fn compile_body(&mut self, body: Node) -> Result<ByteCode, Error> {
+        let mut btc = self.clear();
+        btc.compile_node(body)?;
+        self.variables = btc.variables;
+        Ok(btc.bytecode)
+    }
+
+    fn compile_call(
+        &mut self,
+        node_to_call: Node,
+        args: Vec<Node>,
+        named: Vec<(Token, Node)>,
+    ) -> Result<(), Error> {
+        let mut named_ids = vec![];
+        for (name, _) in &named {
+            let id = self.variable(name.value.into_string());
+            if named_ids.contains(&id) {
+                return Err(Error::new(
+                    "Argument Error",
+                    name.pos_start,
+                    name.pos_end,
+                    "Argument given more than once",
+                ));
+            }
+            named_ids.push(id);
+        }
+        if let Node::VarAccessNode { token } = &node_to_call {
+            let id = self.variable(token.value.into_string());
+            if let Some(params) = self.signatures.get(&id) {
+                let names = &self.variables;
+                let name_of = |id: u16| {
+                    names
+                        .iter()
+                        .find(|(_, v)| **v == id)
+                        .map(|(k, _)| k.clone())
+                        .unwrap_or_default()
+                };
+                if let Err(e) = params.check(args.len(), &named_ids, name_of) {
+                    return Err(Error::new(
+                        "Argument Error",
+                        token.pos_start,
+                        token.pos_end,
+                        Box::leak(e.into_boxed_str()),
+                    ));
+                }
+            }
+        }
+
+        self.add_instruction(OpCode::OpBlockStart);
+        // `f?.(args)` has to check `f` before the arguments are evaluated, so it's kept in
+        // a hidden slot until they are
+        let callee = match &node_to_call {
+            Node::NullSafeNode { .. } => {
+                self.open_calls.push(0);
+                self.compile_node(node_to_call.clone())?;
+                self.open_calls.pop();
+                let idx = self.add_constant(Constants::Boolean(false));
                 self.add_instruction(OpCode::OpConstant(idx));
-                let id = self.variable(name.value.into_string());
+                let id = self.variable(format!("@callee{}", self.hidden));
+                self.add_instruction(OpCode::OpVarAssign(id));
+                self.hidden += 1;
+                Some(id)
+            }
+            _ => None,
+        };
+        let mut array = vec![];
+        for arg in args {
+            let mut array_btc = self.clear();
+            array_btc.compile_node(arg)?;
+            array.push(array_btc.bytecode);
+            self.variables = array_btc.variables;
+        }
+        let idx = self.add_constant(Constants::RawArray(array));
+        self.add_instruction(OpCode::OpConstant(idx));
+        let has_named = !named.is_empty();
+        if has_named {
+            let mut object = HashMap::new();
+            for (id, (_, arg)) in named_ids.into_iter().zip(named) {
+                let mut arg_btc = self.clear();
+                arg_btc.compile_node(arg)?;
+                object.insert(id as usize, arg_btc.bytecode);
+                self.variables = arg_btc.variables;
+            }
+            let idx = self.add_constant(Constants::RawObject(object));
+            self.add_instruction(OpCode::OpConstant(idx));
+        }
+        match callee {
+            Some(id) => {
+                self.hidden -= 1;
                 self.add_instruction(OpCode::OpVarAccess(id));
+            }
+            None => {
+                // the arguments are already on the stack when the callee is evaluated
+                self.open_calls.push(if has_named { 2 } else { 1 });
+                self.compile_node(node_to_call)?;
+                self.open_calls.pop();
+            }
+        }
+        self.add_instruction(OpCode::OpBlockEnd);
+        self.add_instruction(if has_named {
+            OpCode::OpCallNamed
+        } else {
+            OpCode::OpCall
+        });
+        Ok(())
+    }
+
+    fn compile_members(
+        &mut self,
+        members: &[(Token, Node)],
+    ) -> Result<HashMap<usize, ByteCode>, Error> {
+        let mut compiled = HashMap::new();
+        for (name, body) in members {
+            let mut btc = self.clear();
+            let id = btc.variable(name.value.into_string()) as usize;
+            btc.compile_node(body.clone())?;
+            self.variables = btc.variables.clone();
+            compiled.insert(id, btc.bytecode);
+        }
+        Ok(compiled)
+    }
+
+    fn start_loop(&mut self, label: Option<Token>, start: u16) {
+        self.loops.push(Loop {
+            label: label.map(|l| l.value.into_string()),
+            scope_depth: self.scope_depth,
+            try_depth: self.trys.len(),
+            start,
+            breaks: vec![],
+        });
+    }
+
+    fn end_loop(&mut self) {
+        let lp = self.loops.pop().unwrap();
+        for jump in lp.breaks {
+            self.patch_jump(jump, None);
+        }
+    }
+
+    fn find_loop(&self, token: &Token, label: &Option<Token>) -> Result<usize, Error> {
+        if self.loops.is_empty() {
+            return Err(Error::new(
+                "Invalid Syntax",
+                token.pos_start,
+                token.pos_end,
+                Box::leak(
+                    format!("'{}' outside of a loop", token.value.into_string()).into_boxed_str(),
+                ),
+            ));
+        }
+
+        match label {
+            Some(l) => {
+                let name = l.value.into_string();
+                self.loops
+                    .iter()
+                    .rposition(|lp| lp.label.as_ref() == Some(&name))
+                    .ok_or_else(|| {
+                        Error::new(
+                            "Invalid Syntax",
+                            l.pos_start,
+                            l.pos_end,
+                            Box::leak(format!("Undefined loop label '{}'", name).into_boxed_str()),
+                        )
+                    })
+            }
+            None => Ok(self.loops.len() - 1),
+        }
+    }
+
+    fn start_try(&mut self, finally: Option<Node>) -> u16 {
+        let handler = self.add_instruction(OpCode::OpTry(0));
+        self.trys.push(TryFrame {
+            scope_depth: self.scope_depth,
+            finally,
+        });
+        handler
+    }
+
+    fn end_try(&mut self) {
+        self.trys.pop();
+        self.add_instruction(OpCode::OpEndTry);
+    }
+
+    // Leaves every handler above `try_depth`, running their finally blocks on the
+    // way out, then closes the scopes down to `scope_depth` when one is given.
+    fn unwind(&mut self, scope_depth: Option<usize>, try_depth: usize) -> Result<(), Error> {
+        let current = self.scope_depth;
+        let trys = self.trys.clone();
+        while self.trys.len() > try_depth {
+            let frame = self.trys.pop().unwrap();
+            if scope_depth.is_some() {
+                for _ in frame.scope_depth..self.scope_depth {
+                    self.add_instruction(OpCode::OpBlockEnd);
+                }
+            }
+            self.add_instruction(OpCode::OpEndTry);
+            if let Some(finally) = frame.finally {
+                self.add_instruction(OpCode::OpBlockStart);
+                self.compile_node(finally)?;
+                self.add_instruction(OpCode::OpPop);
+                self.add_instruction(OpCode::OpBlockEnd);
+            }
+        }
+        if let Some(depth) = scope_depth {
+            for _ in depth..self.scope_depth {
                 self.add_instruction(OpCode::OpBlockEnd);
-                self.add_instruction(OpCode::OpCall);
             }
         }
+        self.scope_depth = current;
+        self.trys = trys;
+        Ok(())
+    }
+
+    fn compile_binary_op(&mut self, op_token: &Token) {
+        match op_token.r#type {
+            Tokens::Plus | Tokens::PlusEquals => self.add_instruction(OpCode::OpAdd),
+            Tokens::Minus | Tokens::MinusEquals => self.add_instruction(OpCode::OpSubtract),
+            Tokens::Multiply | Tokens::MultiplyEquals => self.add_instruction(OpCode::OpMultiply),
+            Tokens::Divide | Tokens::DivideEquals => self.add_instruction(OpCode::OpDivide),
+            Tokens::Power | Tokens::PowerEquals => self.add_instruction(OpCode::OpPower),
+            Tokens::Modulo | Tokens::ModuloEquals => self.add_instruction(OpCode::OpModulo),
+            Tokens::FloorDivide => self.add_instruction(OpCode::OpFloorDivide),
+            Tokens::BitAnd => self.add_instruction(OpCode::OpBitAnd),
+            Tokens::BitOr => self.add_instruction(OpCode::OpBitOr),
+            Tokens::BitXor => self.add_instruction(OpCode::OpBitXor),
+            Tokens::LeftShift => self.add_instruction(OpCode::OpLeftShift),
+            Tokens::RightShift => self.add_instruction(OpCode::OpRightShift),
+            Tokens::DoubleDot => self.add_instruction(OpCode::OpRange),
+            Tokens::DoubleDotEquals => self.add_instruction(OpCode::OpRangeInclusive),
+            Tokens::DoubleEquals => self.add_instruction(OpCode::OpEquals),
+            Tokens::NotEquals => self.add_instruction(OpCode::OpNotEquals),
+            Tokens::GreaterThan => self.add_instruction(OpCode::OpGreaterThan),
+            Tokens::GreaterThanEquals => self.add_instruction(OpCode::OpGreaterThanEquals),
+            Tokens::LessThan => self.add_instruction(OpCode::OpLessThan),
+            Tokens::LessThanEquals => self.add_instruction(OpCode::OpLessThanEquals),
+            _ => 0,
+        };
+
+        if op_token.matches(Tokens::Keyword, DynType::String("instanceof".to_string())) {
+            self.add_instruction(OpCode::OpInstanceOf);
+        }
     }
 
     fn patch_jump_if_false(&mut self, idx: u16, new: Option<u16>) {
@@ -476,6 +1844,13 @@ impl ByteCodeGen {
     pub fn clear(&self) -> Self {
         let mut cl = self.clone();
         cl.bytecode = ByteCode::new();
+        cl.loops = vec![];
+        cl.trys = vec![];
+        cl.scope_depth = 0;
+        cl.exports = None;
+        cl.imports = vec![];
+        cl.chains = vec![];
+        cl.open_calls = vec![];
         cl
     }
 }
@@ -12,7 +12,7 @@
 */
 
 #![allow(unused_assignments)]
-use bzs_shared::{DynType, Error, Position, Token, Tokens};
+use bzs_shared::{BigInt, DynType, Error, Position, Token, Tokens};
 
 pub fn get_keywords() -> Vec<String> {
     vec![
@@ -25,12 +25,28 @@ pub fn get_keywords() -> Vec<String> {
         string("else"),
         string("for"),
         string("to"),
+        string("in"),
         string("step"),
         string("while"),
         string("fun"),
         string("return"),
+        string("break"),
+        string("continue"),
+        string("try"),
+        string("catch"),
+        string("finally"),
+        string("throw"),
         string("class"),
         string("new"),
+        string("extends"),
+        string("super"),
+        string("instanceof"),
+        string("static"),
+        string("import"),
+        string("export"),
+        string("match"),
+        string("enum"),
+        string("null"),
     ]
 }
 
@@ -72,6 +88,10 @@ impl Lexer {
         lexer
     }
 
+    fn peek(&self) -> Option<char> {
+        self.text.chars().nth(self.position.index + 1)
+    }
+
     fn advance(&mut self) {
         self.position.advance();
         if self.text.len() > self.position.index {
@@ -108,20 +128,16 @@ impl Lexer {
             }
 
             let token = match self.current_char.unwrap() {
-                '+' => Tokens::Plus,
-                '-' => Tokens::Minus,
-                '*' => Tokens::Multiply,
-                '/' => Tokens::Divide,
+                '~' => Tokens::BitNot,
                 '(' => Tokens::LeftParenthesis,
                 ')' => Tokens::RightParenthesis,
                 '{' => Tokens::LeftCurlyBraces,
                 '}' => Tokens::RightCurlyBraces,
                 '[' => Tokens::LeftSquareBraces,
                 ']' => Tokens::RightSquareBraces,
-                '^' => Tokens::Power,
                 ':' => Tokens::Colon,
                 ',' => Tokens::Comma,
-                '.' => Tokens::Dot,
+                '#' => Tokens::Hash,
                 _ => Tokens::Unknown,
             };
 
@@ -134,6 +150,18 @@ impl Lexer {
                     '<' => tokens.push(self.make_less_than()),
                     '>' => tokens.push(self.make_greater_than()),
                     '=' => tokens.push(self.make_equals()),
+                    '+' => tokens.push(self.make_operator(Tokens::Plus, Tokens::PlusEquals)),
+                    '-' => tokens.push(self.make_operator(Tokens::Minus, Tokens::MinusEquals)),
+                    '*' => {
+                        tokens.push(self.make_operator(Tokens::Multiply, Tokens::MultiplyEquals))
+                    }
+                    '%' => tokens.push(self.make_operator(Tokens::Modulo, Tokens::ModuloEquals)),
+                    '.' => tokens.push(self.make_dot()),
+                    '?' => tokens.push(self.make_question()?),
+                    '/' => tokens.push(self.make_divide()),
+                    '^' => tokens.push(self.make_power()),
+                    '|' => tokens.push(self.make_or()),
+                    '&' => tokens.push(self.make_and()),
                     '\'' => {
                         let result = self.make_char();
                         match result {
@@ -143,31 +171,13 @@ impl Lexer {
                             }
                         };
                     }
-                    '|' => {
-                        let result = self.make_or();
-                        match result {
-                            Ok(token) => tokens.push(token),
-                            Err(e) => {
-                                return Err(e);
-                            }
-                        };
-                    }
-                    '&' => {
-                        let result = self.make_and();
-                        match result {
-                            Ok(token) => tokens.push(token),
-                            Err(e) => {
-                                return Err(e);
-                            }
-                        };
-                    }
                     _ => {
                         let no = self.current_char.unwrap().to_digit(36);
                         if no.is_some() {
                             if get_number().contains(&no.unwrap())
                                 || self.current_char.unwrap() == '.'
                             {
-                                tokens.push(self.make_number());
+                                tokens.push(self.make_number()?);
                             } else if get_ascii_letters()
                                 .contains(&self.current_char.unwrap().to_string().as_str())
                             {
@@ -175,6 +185,8 @@ impl Lexer {
                             } else {
                                 token_is_unknown = true;
                             }
+                        } else if self.current_char.unwrap() == '_' {
+                            tokens.push(self.make_identifiers());
                         } else {
                             token_is_unknown = true;
                         }
@@ -211,7 +223,7 @@ impl Lexer {
         Ok(tokens)
     }
 
-    fn make_number(&mut self) -> Token {
+    fn make_number(&mut self) -> Result<Token, Error> {
         let mut str_num = String::new();
         let mut dot_count = 0;
         let start = self.position.clone();
@@ -223,27 +235,44 @@ impl Lexer {
                 break;
             }
             if self.current_char.unwrap() == '.' {
+                if self.peek() == Some('.') {
+                    break;
+                }
                 dot_count += 1;
             }
             str_num.push(self.current_char.unwrap());
             self.advance();
         }
 
-        return if dot_count > 0 {
-            Token::new(
-                Tokens::Float,
-                start,
-                self.position.clone(),
-                DynType::Float(str_num.parse::<f64>().unwrap()),
-            )
+        // Ints too large for an i128 and the ones ending with `n` are BigInts
+        let value = if dot_count > 0 {
+            str_num.parse::<f64>().ok().map(DynType::Float)
+        } else if let Some(digits) = str_num.strip_suffix('n') {
+            BigInt::parse(digits).map(DynType::BigInt)
         } else {
-            Token::new(
-                Tokens::Int,
-                start,
-                self.position.clone(),
-                DynType::Int(str_num.parse::<i128>().unwrap()),
-            )
+            match str_num.parse::<i128>() {
+                Ok(i) => Some(DynType::Int(i)),
+                Err(_) => BigInt::parse(&str_num).map(DynType::BigInt),
+            }
         };
+        match value {
+            Some(value) => Ok(Token::new(
+                if dot_count > 0 {
+                    Tokens::Float
+                } else {
+                    Tokens::Int
+                },
+                start,
+                self.position,
+                value,
+            )),
+            None => Err(Error::new(
+                "Illegal Character",
+                start,
+                self.position,
+                "Invalid number",
+            )),
+        }
     }
 
     fn make_string(&mut self) -> Token {
@@ -337,12 +366,16 @@ impl Lexer {
         self.advance();
 
         if self.current_char.unwrap_or(' ') == '=' {
+            self.advance();
             return Token::new(
                 Tokens::LessThanEquals,
                 start,
                 self.position.clone(),
                 DynType::None,
             );
+        } else if self.current_char.unwrap_or(' ') == '<' {
+            self.advance();
+            return Token::new(Tokens::LeftShift, start, self.position, DynType::None);
         }
 
         Token::new(
@@ -358,12 +391,16 @@ impl Lexer {
         self.advance();
 
         if self.current_char.unwrap_or(' ') == '=' {
+            self.advance();
             return Token::new(
                 Tokens::GreaterThanEquals,
                 start,
                 self.position.clone(),
                 DynType::None,
             );
+        } else if self.current_char.unwrap_or(' ') == '>' {
+            self.advance();
+            return Token::new(Tokens::RightShift, start, self.position, DynType::None);
         }
 
         Token::new(
@@ -396,48 +433,120 @@ impl Lexer {
         )
     }
 
-    fn make_or(&mut self) -> Result<Token, Error> {
+    fn make_operator(&mut self, op: Tokens, op_equals: Tokens) -> Token {
+        let start = self.position;
+        self.advance();
+
+        if self.current_char.unwrap_or(' ') == '=' {
+            self.advance();
+            return Token::new(op_equals, start, self.position, DynType::None);
+        }
+
+        Token::new(op, start, self.position, DynType::None)
+    }
+
+    fn make_dot(&mut self) -> Token {
+        let start = self.position;
+        self.advance();
+
+        if self.current_char.unwrap_or(' ') == '.' {
+            self.advance();
+            if self.current_char.unwrap_or(' ') == '=' {
+                self.advance();
+                return Token::new(Tokens::DoubleDotEquals, start, self.position, DynType::None);
+            }
+            if self.current_char.unwrap_or(' ') == '.' {
+                self.advance();
+                return Token::new(Tokens::TripleDot, start, self.position, DynType::None);
+            }
+            return Token::new(Tokens::DoubleDot, start, self.position, DynType::None);
+        }
+
+        Token::new(Tokens::Dot, start, self.position, DynType::None)
+    }
+
+    fn make_question(&mut self) -> Result<Token, Error> {
+        let start = self.position;
+        self.advance();
+
+        let token = match self.current_char.unwrap_or(' ') {
+            '.' => Tokens::QuestionDot,
+            '?' => Tokens::DoubleQuestion,
+            _ => {
+                return Err(Error::new(
+                    "Illegal Character",
+                    start,
+                    self.position,
+                    "Expected '?.' or '??'",
+                ))
+            }
+        };
+        self.advance();
+        Ok(Token::new(token, start, self.position, DynType::None))
+    }
+
+    fn make_divide(&mut self) -> Token {
+        let start = self.position;
+        self.advance();
+
+        if self.current_char.unwrap_or(' ') == '/' {
+            self.advance();
+            return Token::new(Tokens::FloorDivide, start, self.position, DynType::None);
+        } else if self.current_char.unwrap_or(' ') == '=' {
+            self.advance();
+            return Token::new(Tokens::DivideEquals, start, self.position, DynType::None);
+        }
+
+        Token::new(Tokens::Divide, start, self.position, DynType::None)
+    }
+
+    fn make_power(&mut self) -> Token {
+        let start = self.position;
+        self.advance();
+
+        if self.current_char.unwrap_or(' ') == '^' {
+            self.advance();
+            return Token::new(Tokens::BitXor, start, self.position, DynType::None);
+        } else if self.current_char.unwrap_or(' ') == '=' {
+            self.advance();
+            return Token::new(Tokens::PowerEquals, start, self.position, DynType::None);
+        }
+
+        Token::new(Tokens::Power, start, self.position, DynType::None)
+    }
+
+    fn make_or(&mut self) -> Token {
         let start = self.position.clone();
         self.advance();
 
         if self.current_char.unwrap_or(' ') == '|' {
             self.advance();
-            return Ok(Token::new(
+            return Token::new(
                 Tokens::Keyword,
                 start,
                 self.position.clone(),
                 DynType::String("or".to_string()),
-            ));
+            );
         }
 
-        Err(Error::new(
-            "Expected Character",
-            start,
-            self.position.clone(),
-            "Expected one more '|'",
-        ))
+        Token::new(Tokens::BitOr, start, self.position, DynType::None)
     }
 
-    fn make_and(&mut self) -> Result<Token, Error> {
+    fn make_and(&mut self) -> Token {
         let start = self.position.clone();
         self.advance();
 
         if self.current_char.unwrap_or(' ') == '&' {
             self.advance();
-            return Ok(Token::new(
+            return Token::new(
                 Tokens::Keyword,
                 start,
                 self.position.clone(),
                 DynType::String("and".to_string()),
-            ));
+            );
         }
 
-        Err(Error::new(
-            "Expected Character",
-            start,
-            self.position.clone(),
-            "Expected one more '&'",
-        ))
+        Token::new(Tokens::BitAnd, start, self.position, DynType::None)
     }
 
     fn make_identifiers(&mut self) -> Token {
@@ -488,13 +597,12 @@ impl Lexer {
             }
         }
 
+        // the newline is left for the statement the comment ends
         while self.current_char.is_some() {
             if self.current_char.unwrap() == '\n' {
                 break;
             }
             self.advance();
         }
-
-        self.advance();
     }
 }
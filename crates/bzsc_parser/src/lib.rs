@@ -11,7 +11,10 @@
    limitations under the License.
 */
 
-use bzs_shared::{DynType, Error, Node, Token, Tokens};
+use bzs_shared::{BigInt, DynType, Error, Node, Pattern, Position, Token, Tokens};
+
+// The positional arguments of a call, and the named ones with their names
+type CallArgs = (Vec<Node>, Vec<(Token, Node)>);
 
 #[derive(Debug, Clone)]
 pub struct ParseResult {
@@ -120,6 +123,11 @@ impl Parser {
             self.advance();
         }
 
+        // an empty body, `{ }`
+        if self.current_token.r#type == Tokens::RightCurlyBraces {
+            return res.success(Node::Statements { statements });
+        }
+
         let mut statement = res.register(self.statement());
         if res.error.is_some() {
             return res;
@@ -174,6 +182,157 @@ impl Parser {
             });
         }
 
+        if self
+            .current_token
+            .matches(Tokens::Keyword, DynType::String("import".to_string()))
+        {
+            return self.import_stmt();
+        }
+
+        if self
+            .current_token
+            .matches(Tokens::Keyword, DynType::String("export".to_string()))
+        {
+            let token = self.current_token.clone();
+            res.register_advancement();
+            self.advance();
+
+            let expr = res.register(self.expr());
+            if res.error.is_some() {
+                return res;
+            }
+            return match expr.unwrap() {
+                node @ Node::VarAssignNode { .. }
+                | node @ Node::FunDef { name: Some(_), .. }
+                | node @ Node::ClassDefNode { .. }
+                | node @ Node::EnumDefNode { .. } => res.success(Node::ExportNode {
+                    token,
+                    node: Box::new(node),
+                }),
+                _ => res.failure(Error::new(
+                    "Invalid Syntax",
+                    token.pos_start,
+                    self.current_token.pos_start,
+                    "Expected a variable, function, class or enum after 'export'",
+                )),
+            };
+        }
+
+        if self
+            .current_token
+            .matches(Tokens::Keyword, DynType::String("throw".to_string()))
+        {
+            let token = self.current_token.clone();
+            res.register_advancement();
+            self.advance();
+
+            let expr = res.register(self.expr());
+            if res.error.is_some() {
+                return res;
+            }
+
+            return res.success(Node::ThrowNode {
+                token,
+                value: Box::new(expr.unwrap()),
+            });
+        }
+
+        if self
+            .current_token
+            .matches(Tokens::Keyword, DynType::String("break".to_string()))
+            || self
+                .current_token
+                .matches(Tokens::Keyword, DynType::String("continue".to_string()))
+        {
+            let token = self.current_token.clone();
+            res.register_advancement();
+            self.advance();
+
+            let mut label = None;
+            if self.current_token.r#type == Tokens::Identifier {
+                label = Some(self.current_token.clone());
+                res.register_advancement();
+                self.advance();
+            }
+
+            return res.success(if token.value.into_string() == "break" {
+                Node::BreakNode { token, label }
+            } else {
+                Node::ContinueNode { token, label }
+            });
+        }
+
+        if self.current_token.r#type == Tokens::Identifier
+            && self.tokens[self.token_index + 1].r#type == Tokens::Colon
+        {
+            let label_token = self.current_token.clone();
+            res.register_advancement();
+            self.advance();
+            res.register_advancement();
+            self.advance();
+
+            let loop_node = if self
+                .current_token
+                .matches(Tokens::Keyword, DynType::String("for".to_string()))
+            {
+                res.register(self.for_expr())
+            } else if self
+                .current_token
+                .matches(Tokens::Keyword, DynType::String("while".to_string()))
+            {
+                res.register(self.while_expr())
+            } else {
+                return res.failure(Error::new(
+                    "Invalid Syntax",
+                    self.current_token.pos_start,
+                    self.current_token.pos_end,
+                    "Expected 'for' or 'while' after loop label",
+                ));
+            };
+            if res.error.is_some() {
+                return res;
+            }
+
+            return res.success(match loop_node.unwrap() {
+                Node::ForNode {
+                    var_name_token,
+                    start_value,
+                    end_value,
+                    body_node,
+                    step_value_node,
+                    ..
+                } => Node::ForNode {
+                    var_name_token,
+                    start_value,
+                    end_value,
+                    body_node,
+                    step_value_node,
+                    label: Some(label_token),
+                },
+                Node::ForInNode {
+                    var_name_token,
+                    iterable,
+                    body_node,
+                    ..
+                } => Node::ForInNode {
+                    var_name_token,
+                    iterable,
+                    body_node,
+                    label: Some(label_token),
+                },
+                Node::WhileNode {
+                    condition_node,
+                    body_node,
+                    ..
+                } => Node::WhileNode {
+                    condition_node,
+                    body_node,
+                    label: Some(label_token),
+                },
+                _ => unreachable!(),
+            });
+        }
+
         let expr = res.register(self.expr());
         if res.error.is_some() {
             return res;
@@ -202,6 +361,36 @@ impl Parser {
             res.register_advancement();
             self.advance();
 
+            let reassignable = var_type == "var";
+            if [Tokens::LeftSquareBraces, Tokens::LeftCurlyBraces]
+                .contains(&self.current_token.r#type)
+            {
+                let pattern = match self.pattern(&mut res) {
+                    Ok(pattern) => pattern,
+                    Err(e) => return res.failure(e),
+                };
+                if self.current_token.r#type != Tokens::Equals {
+                    return res.failure(Error::new(
+                        "Invalid Syntax",
+                        self.current_token.pos_start,
+                        self.current_token.pos_end,
+                        "Expected '='",
+                    ));
+                }
+                res.register_advancement();
+                self.advance();
+
+                let expr = res.register(self.expr());
+                if res.error.is_some() {
+                    return res;
+                }
+                return res.success(Node::DestructureNode {
+                    pattern,
+                    value: Box::new(expr.unwrap()),
+                    reassignable,
+                });
+            }
+
             if self.current_token.r#type != Tokens::Identifier {
                 return res.failure(Error::new(
                     "Invalid Syntax Error",
@@ -232,11 +421,6 @@ impl Parser {
                 return res;
             }
 
-            let reassignable = if var_type == String::from("var") {
-                true
-            } else {
-                false
-            };
             return res.success(Node::VarAssignNode {
                 name: var_name.clone(),
                 value: Box::new(expr.unwrap()),
@@ -244,11 +428,52 @@ impl Parser {
             });
         }
 
-        let mut left = res.register(self.comp_expr());
+        let mut left = res.register(self.coalesce_expr());
         if res.error.is_some() {
             return res;
         }
 
+        if [
+            Tokens::PlusEquals,
+            Tokens::MinusEquals,
+            Tokens::MultiplyEquals,
+            Tokens::DivideEquals,
+            Tokens::ModuloEquals,
+            Tokens::PowerEquals,
+        ]
+        .contains(&self.current_token.r#type)
+        {
+            let op_token = self.current_token.clone();
+            let target = left.unwrap();
+            match target {
+                Node::VarAccessNode { .. }
+                | Node::ObjectPropAccess { .. }
+                | Node::ArrayAcess { .. } => (),
+                _ => {
+                    return res.failure(Error::new(
+                        "Invalid Syntax",
+                        pos_start,
+                        op_token.pos_end,
+                        "Expected a variable, property or array element before compound assignment",
+                    ))
+                }
+            }
+
+            res.register_advancement();
+            self.advance();
+
+            let value = res.register(self.expr());
+            if res.error.is_some() {
+                return res;
+            }
+
+            return res.success(Node::CompoundAssignNode {
+                target: Box::new(target),
+                op_token,
+                value: Box::new(value.unwrap()),
+            });
+        }
+
         while self
             .current_token
             .clone()
@@ -261,7 +486,7 @@ impl Parser {
             let op_token = self.current_token.clone();
             res.register_advancement();
             self.advance();
-            let right = res.register(self.comp_expr());
+            let right = res.register(self.coalesce_expr());
             if res.error.is_some() {
                 return res;
             }
@@ -284,6 +509,33 @@ impl Parser {
         res.success(left.unwrap())
     }
 
+    fn coalesce_expr(&mut self) -> ParseResult {
+        let mut res = ParseResult::new();
+        let mut left = res.register(self.comp_expr());
+        if res.error.is_some() {
+            return res;
+        }
+
+        while self.current_token.r#type == Tokens::DoubleQuestion {
+            let op_token = self.current_token.clone();
+            res.register_advancement();
+            self.advance();
+
+            let right = res.register(self.comp_expr());
+            if res.error.is_some() {
+                return res;
+            }
+
+            left = Option::from(Node::BinOpNode {
+                left: Box::new(left.unwrap()),
+                right: Box::new(right.unwrap()),
+                op_token,
+            });
+        }
+
+        res.success(left.unwrap())
+    }
+
     fn comp_expr(&mut self) -> ParseResult {
         let mut res = ParseResult::new();
         let pos_start = self.current_token.clone().pos_start;
@@ -308,7 +560,7 @@ impl Parser {
             });
         }
 
-        let mut left = res.register(self.arith_expr());
+        let mut left = res.register(self.range_expr());
         if res.error.is_some() {
             return res;
         }
@@ -322,12 +574,15 @@ impl Parser {
             Tokens::GreaterThanEquals,
         ]
         .contains(&self.current_token.r#type)
+            || self
+                .current_token
+                .matches(Tokens::Keyword, DynType::String("instanceof".to_string()))
         {
             let op_token = self.current_token.clone();
             res.register_advancement();
             self.advance();
 
-            let right = res.register(self.arith_expr());
+            let right = res.register(self.range_expr());
             if res.error.is_some() {
                 return res;
             }
@@ -343,33 +598,33 @@ impl Parser {
                 "Invalid Syntax",
                 pos_start,
                 self.current_token.pos_end.clone(),
-                "A Int or Float or Identifier, '+', '-', '(', 'not', '!' was Expected",
+                "A Int or Float or Identifier, '+', '-', '~', '(', 'not', '!' was Expected",
             ));
         }
         res.success(left.unwrap())
     }
 
-    fn arith_expr(&mut self) -> ParseResult {
+    fn range_expr(&mut self) -> ParseResult {
         let mut res = ParseResult::new();
 
-        let mut left = res.register(self.term());
+        let left = res.register(self.bit_or_expr());
         if res.error.is_some() {
             return res;
         }
 
-        while [Tokens::Plus, Tokens::Minus].contains(&self.current_token.r#type) {
+        if [Tokens::DoubleDot, Tokens::DoubleDotEquals].contains(&self.current_token.r#type) {
             let op_token = self.current_token.clone();
             res.register_advancement();
             self.advance();
 
-            let right = res.register(self.term());
+            let right = res.register(self.bit_or_expr());
             if res.error.is_some() {
                 return res;
             }
 
-            left = Option::from(Node::BinOpNode {
-                left: Box::new(left.clone().unwrap()),
-                right: Box::new(right.clone().unwrap()),
+            return res.success(Node::BinOpNode {
+                left: Box::new(left.unwrap()),
+                right: Box::new(right.unwrap()),
                 op_token,
             });
         }
@@ -377,26 +632,27 @@ impl Parser {
         res.success(left.unwrap())
     }
 
-    fn term(&mut self) -> ParseResult {
+    fn bit_or_expr(&mut self) -> ParseResult {
         let mut res = ParseResult::new();
-        let mut left = res.register(self.factor());
+
+        let mut left = res.register(self.bit_xor_expr());
         if res.error.is_some() {
             return res;
         }
 
-        while [Tokens::Multiply, Tokens::Divide].contains(&self.current_token.r#type) {
+        while self.current_token.r#type == Tokens::BitOr {
             let op_token = self.current_token.clone();
             res.register_advancement();
             self.advance();
 
-            let right = res.register(self.factor());
+            let right = res.register(self.bit_xor_expr());
             if res.error.is_some() {
                 return res;
             }
 
             left = Option::from(Node::BinOpNode {
-                left: Box::new(left.clone().unwrap()),
-                right: Box::new(right.clone().unwrap()),
+                left: Box::new(left.unwrap()),
+                right: Box::new(right.unwrap()),
                 op_token,
             });
         }
@@ -404,45 +660,55 @@ impl Parser {
         res.success(left.unwrap())
     }
 
-    fn factor(&mut self) -> ParseResult {
+    fn bit_xor_expr(&mut self) -> ParseResult {
         let mut res = ParseResult::new();
-        let token = self.current_token.clone();
 
-        if [Tokens::Plus, Tokens::Minus].contains(&self.current_token.r#type) {
+        let mut left = res.register(self.bit_and_expr());
+        if res.error.is_some() {
+            return res;
+        }
+
+        while self.current_token.r#type == Tokens::BitXor {
+            let op_token = self.current_token.clone();
             res.register_advancement();
             self.advance();
-            let factor = res.register(self.factor());
+
+            let right = res.register(self.bit_and_expr());
             if res.error.is_some() {
                 return res;
             }
-            return res.success(Node::UnaryNode {
-                op_token: token.clone(),
-                node: Box::new(factor.clone().unwrap()),
+
+            left = Option::from(Node::BinOpNode {
+                left: Box::new(left.unwrap()),
+                right: Box::new(right.unwrap()),
+                op_token,
             });
         }
-        self.power()
+
+        res.success(left.unwrap())
     }
 
-    fn power(&mut self) -> ParseResult {
+    fn bit_and_expr(&mut self) -> ParseResult {
         let mut res = ParseResult::new();
-        let mut left = res.register(self.call());
+
+        let mut left = res.register(self.shift_expr());
         if res.error.is_some() {
             return res;
         }
 
-        while self.current_token.r#type == Tokens::Power {
+        while self.current_token.r#type == Tokens::BitAnd {
             let op_token = self.current_token.clone();
             res.register_advancement();
             self.advance();
 
-            let right = res.register(self.factor());
+            let right = res.register(self.shift_expr());
             if res.error.is_some() {
                 return res;
             }
 
             left = Option::from(Node::BinOpNode {
-                left: Box::new(left.clone().unwrap()),
-                right: Box::new(right.clone().unwrap()),
+                left: Box::new(left.unwrap()),
+                right: Box::new(right.unwrap()),
                 op_token,
             });
         }
@@ -450,80 +716,270 @@ impl Parser {
         res.success(left.unwrap())
     }
 
-    fn call(&mut self) -> ParseResult {
+    fn shift_expr(&mut self) -> ParseResult {
         let mut res = ParseResult::new();
-        let atom = res.register(self.obj_prop_expr());
+
+        let mut left = res.register(self.arith_expr());
         if res.error.is_some() {
             return res;
         }
 
-        if self.current_token.r#type == Tokens::LeftParenthesis {
-            let mut arg_nodes: Vec<Node> = vec![];
+        while [Tokens::LeftShift, Tokens::RightShift].contains(&self.current_token.r#type) {
+            let op_token = self.current_token.clone();
             res.register_advancement();
             self.advance();
 
-            if self.current_token.r#type == Tokens::RightParenthesis {
-                res.register_advancement();
-                self.advance();
-            } else {
-                let expr = res.register(self.expr());
-                if res.error.is_some() {
-                    return res.failure(Error::new(
-                        "Invalid Syntax",
-                        self.current_token.pos_start.clone(),
-                        self.current_token.pos_end.clone(),
-                        "Expected ')', 'var', int, float, identifier, '+', '-' or ','",
-                    ));
-                }
-                arg_nodes.push(expr.unwrap());
-
-                while self.current_token.r#type == Tokens::Comma {
-                    res.register_advancement();
-                    self.advance();
-
-                    let expr = res.register(self.expr());
-                    if res.error.is_some() {
-                        return res.failure(Error::new(
-                            "Invalid Syntax",
-                            self.current_token.pos_start.clone(),
-                            self.current_token.pos_end.clone(),
-                            "Expected ')', 'var', int, float, identifier, '+', '-' or ','",
-                        ));
-                    }
-                    arg_nodes.push(expr.unwrap());
-                }
-
-                if self.current_token.r#type != Tokens::RightParenthesis {
-                    return res.failure(Error::new(
-                        "Invalid Syntax",
-                        self.current_token.pos_start.clone(),
-                        self.current_token.pos_end.clone(),
-                        "Expected ')' or ','",
-                    ));
-                }
-                res.register_advancement();
-                self.advance();
+            let right = res.register(self.arith_expr());
+            if res.error.is_some() {
+                return res;
             }
-            return res.success(Node::CallNode {
-                node_to_call: Box::new(atom.clone().unwrap()),
-                args: arg_nodes,
+
+            left = Option::from(Node::BinOpNode {
+                left: Box::new(left.unwrap()),
+                right: Box::new(right.unwrap()),
+                op_token,
             });
-        } else if self.current_token.r#type == Tokens::Dot {
-            self.advance();
-            res.register_advancement();
+        }
 
-            if self.current_token.r#type != Tokens::Identifier {
-                return res.failure(Error::new(
-                    "Invalid Syntax",
-                    self.current_token.pos_start.clone(),
-                    self.current_token.pos_end.clone(),
-                    "Expected identifier",
-                ));
-            }
+        res.success(left.unwrap())
+    }
 
-            let mut id = self.current_token.clone();
+    fn arith_expr(&mut self) -> ParseResult {
+        let mut res = ParseResult::new();
 
-            res.register_advancement();
+        let mut left = res.register(self.term());
+        if res.error.is_some() {
+            return res;
+        }
+
+        while [Tokens::Plus, Tokens::Minus].contains(&self.current_token.r#type) {
+            let op_token = self.current_token.clone();
+            res.register_advancement();
+            self.advance();
+
+            let right = res.register(self.term());
+            if res.error.is_some() {
+                return res;
+            }
+
+            left = Option::from(Node::BinOpNode {
+                left: Box::new(left.clone().unwrap()),
+                right: Box::new(right.clone().unwrap()),
+                op_token,
+            });
+        }
+
+        res.success(left.unwrap())
+    }
+
+    fn term(&mut self) -> ParseResult {
+        let mut res = ParseResult::new();
+        let mut left = res.register(self.factor());
+        if res.error.is_some() {
+            return res;
+        }
+
+        while [
+            Tokens::Multiply,
+            Tokens::Divide,
+            Tokens::FloorDivide,
+            Tokens::Modulo,
+        ]
+        .contains(&self.current_token.r#type)
+        {
+            let op_token = self.current_token.clone();
+            res.register_advancement();
+            self.advance();
+
+            let right = res.register(self.factor());
+            if res.error.is_some() {
+                return res;
+            }
+
+            left = Option::from(Node::BinOpNode {
+                left: Box::new(left.clone().unwrap()),
+                right: Box::new(right.clone().unwrap()),
+                op_token,
+            });
+        }
+
+        res.success(left.unwrap())
+    }
+
+    fn factor(&mut self) -> ParseResult {
+        let mut res = ParseResult::new();
+        let token = self.current_token.clone();
+
+        if [Tokens::Plus, Tokens::Minus, Tokens::BitNot].contains(&self.current_token.r#type) {
+            res.register_advancement();
+            self.advance();
+            let factor = res.register(self.factor());
+            if res.error.is_some() {
+                return res;
+            }
+            return res.success(Node::UnaryNode {
+                op_token: token.clone(),
+                node: Box::new(factor.clone().unwrap()),
+            });
+        }
+        self.power()
+    }
+
+    fn power(&mut self) -> ParseResult {
+        let mut res = ParseResult::new();
+        let mut left = res.register(self.optional_chain());
+        if res.error.is_some() {
+            return res;
+        }
+
+        while self.current_token.r#type == Tokens::Power {
+            let op_token = self.current_token.clone();
+            res.register_advancement();
+            self.advance();
+
+            let right = res.register(self.factor());
+            if res.error.is_some() {
+                return res;
+            }
+
+            left = Option::from(Node::BinOpNode {
+                left: Box::new(left.clone().unwrap()),
+                right: Box::new(right.clone().unwrap()),
+                op_token,
+            });
+        }
+
+        res.success(left.unwrap())
+    }
+
+    // Everything after the first `?.` is part of the chain, so when the value before any
+    // `?.` in it is null the rest is skipped and the whole chain gives null
+    fn optional_chain(&mut self) -> ParseResult {
+        let mut res = ParseResult::new();
+        let base = res.register(self.call());
+        if res.error.is_some() {
+            return res;
+        }
+        if self.current_token.r#type != Tokens::QuestionDot {
+            return res.success(base.unwrap());
+        }
+
+        let mut node = base.unwrap();
+        loop {
+            let optional = self.current_token.r#type == Tokens::QuestionDot;
+            if optional {
+                res.register_advancement();
+                self.advance();
+                node = Node::NullSafeNode {
+                    node: Box::new(node),
+                };
+            } else if self.current_token.r#type == Tokens::Dot {
+                res.register_advancement();
+                self.advance();
+                if self.current_token.r#type != Tokens::Identifier {
+                    return res.failure(Error::new(
+                        "Invalid Syntax",
+                        self.current_token.pos_start,
+                        self.current_token.pos_end,
+                        "Expected identifier",
+                    ));
+                }
+            }
+
+            match self.current_token.r#type {
+                Tokens::Identifier => {
+                    node = Node::ObjectPropAccess {
+                        object: Box::new(node),
+                        property: self.current_token.clone(),
+                    };
+                    res.register_advancement();
+                    self.advance();
+                }
+                Tokens::LeftSquareBraces => {
+                    res.register_advancement();
+                    self.advance();
+                    let index = res.register(self.expr());
+                    if res.error.is_some() {
+                        return res;
+                    }
+                    if self.current_token.r#type != Tokens::RightSquareBraces {
+                        return res.failure(Error::new(
+                            "Invalid Syntax",
+                            self.current_token.pos_start,
+                            self.current_token.pos_end,
+                            "Expected ']'",
+                        ));
+                    }
+                    res.register_advancement();
+                    self.advance();
+                    node = Node::ArrayAcess {
+                        array: Box::new(node),
+                        index: Box::new(index.unwrap()),
+                    };
+                }
+                Tokens::LeftParenthesis => {
+                    let (args, named) = match self.call_args(&mut res) {
+                        Ok(args) => args,
+                        Err(e) => return res.failure(e),
+                    };
+                    node = Node::CallNode {
+                        node_to_call: Box::new(node),
+                        args,
+                        named,
+                    };
+                }
+                _ if optional => {
+                    return res.failure(Error::new(
+                        "Invalid Syntax",
+                        self.current_token.pos_start,
+                        self.current_token.pos_end,
+                        "Expected identifier, '[' or '(' after '?.'",
+                    ))
+                }
+                _ => break,
+            }
+        }
+
+        res.success(Node::OptionalChainNode {
+            chain: Box::new(node),
+        })
+    }
+
+    fn call(&mut self) -> ParseResult {
+        let mut res = ParseResult::new();
+        let atom = res.register(self.obj_prop_expr());
+        if res.error.is_some() {
+            return res;
+        }
+
+        if self.current_token.r#type == Tokens::LeftParenthesis {
+            let (arg_nodes, named) = match self.call_args(&mut res) {
+                Ok(args) => args,
+
+                Err(e) => return res.failure(e),
+            };
+            return res.success(Node::CallNode {
+                node_to_call: Box::new(atom.clone().unwrap()),
+                args: arg_nodes,
+
+                named,
+            });
+        } else if self.current_token.r#type == Tokens::Dot {
+            self.advance();
+            res.register_advancement();
+
+            if self.current_token.r#type != Tokens::Identifier {
+                return res.failure(Error::new(
+                    "Invalid Syntax",
+                    self.current_token.pos_start.clone(),
+                    self.current_token.pos_end.clone(),
+                    "Expected identifier",
+                ));
+            }
+
+            let mut id = self.current_token.clone();
+
+            res.register_advancement();
             self.advance();
 
             if self.current_token.r#type == Tokens::Equals {
@@ -588,30 +1044,10 @@ impl Parser {
             }
             return res.success(l);
         } else if self.current_token.r#type == Tokens::LeftSquareBraces {
-            res.register_advancement();
-            self.advance();
-
-            let index = res.register(self.expr());
-            if res.error.is_some() {
-                return res;
-            }
-
-            if self.current_token.r#type != Tokens::RightSquareBraces {
-                return res.failure(Error::new(
-                    "Invalid Syntax",
-                    self.current_token.pos_start.clone(),
-                    self.current_token.pos_end.clone(),
-                    "Expected ']'",
-                ));
-            }
-
-            res.register_advancement();
-            self.advance();
-
-            return res.success(Node::ArrayAcess {
-                array: Box::new(atom.unwrap()),
-                index: Box::new(index.unwrap()),
-            });
+            return match self.index_suffix(&mut res, atom.unwrap(), self.current_token.pos_start) {
+                Ok(node) => res.success(node),
+                Err(e) => res.failure(e),
+            };
         }
 
         res.success(atom.unwrap())
@@ -704,168 +1140,96 @@ impl Parser {
             }
             return res.success(l);
         } else if self.current_token.r#type == Tokens::LeftParenthesis {
-            let mut arg_nodes: Vec<Node> = vec![];
-            res.register_advancement();
-            self.advance();
-
-            if self.current_token.r#type == Tokens::RightParenthesis {
-                res.register_advancement();
-                self.advance();
-            } else {
-                let expr = res.register(self.expr());
-                if res.error.is_some() {
-                    return res.failure(Error::new(
-                        "Invalid Syntax",
-                        self.current_token.pos_start.clone(),
-                        self.current_token.pos_end.clone(),
-                        "Expected ')', 'var', int, float, identifier, '+', '-' or ','",
-                    ));
-                }
-                arg_nodes.push(expr.unwrap());
-
-                while self.current_token.r#type == Tokens::Comma {
-                    res.register_advancement();
-                    self.advance();
-
-                    let expr = res.register(self.expr());
-                    if res.error.is_some() {
-                        return res.failure(Error::new(
-                            "Invalid Syntax",
-                            self.current_token.pos_start.clone(),
-                            self.current_token.pos_end.clone(),
-                            "Expected ')', 'var', int, float, identifier, '+', '-' or ','",
-                        ));
-                    }
-                    arg_nodes.push(expr.unwrap());
-                }
-
-                if self.current_token.r#type != Tokens::RightParenthesis {
-                    return res.failure(Error::new(
-                        "Invalid Syntax",
-                        self.current_token.pos_start.clone(),
-                        self.current_token.pos_end.clone(),
-                        "Expected ')' or ','",
-                    ));
-                }
-                res.register_advancement();
-                self.advance();
-            }
+            let (arg_nodes, named) = match self.call_args(&mut res) {
+                Ok(args) => args,
+                Err(e) => return res.failure(e),
+            };
             return res.success(Node::CallNode {
                 node_to_call: Box::new(index.clone().unwrap()),
                 args: arg_nodes,
+                named,
             });
         } else if self.current_token.r#type == Tokens::LeftSquareBraces {
-            res.register_advancement();
-            self.advance();
-
-            let idx = res.register(self.expr());
-            if res.error.is_some() {
-                return res;
-            }
-
-            if self.current_token.r#type != Tokens::RightSquareBraces {
-                return res.failure(Error::new(
-                    "Invalid Syntax",
-                    self.current_token.pos_start.clone(),
-                    self.current_token.pos_end.clone(),
-                    "Expected ']'",
-                ));
-            }
-
-            res.register_advancement();
-            self.advance();
-
-            return res.success(Node::ArrayAcess {
-                array: Box::new(index.unwrap()),
-                index: Box::new(idx.unwrap()),
-            });
+            return match self.index_suffix(&mut res, index.unwrap(), self.current_token.pos_start) {
+                Ok(node) => res.success(node),
+                Err(e) => res.failure(e),
+            };
         }
 
         res.success(index.unwrap())
     }
 
-    fn index_expr(&mut self) -> ParseResult {
-        let mut res = ParseResult::new();
-        let pos_start = self.current_token.pos_start.clone();
-        let atom = res.register(self.atom());
-        if res.error.is_some() {
-            return res;
-        }
+    // Parses `[index]` after `array`, or `[index] = value` when the element is assigned
+    fn index_suffix(
+        &mut self,
+        res: &mut ParseResult,
+        array: Node,
+        pos_start: Position,
+    ) -> Result<Node, Error> {
+        res.register_advancement();
+        self.advance();
 
-        if self.current_token.r#type == Tokens::LeftSquareBraces {
-            res.register_advancement();
-            self.advance();
+        let index = res.register(self.expr());
+        if let Some(error) = res.error.clone() {
+            return Err(error);
+        }
 
-            let index = res.register(self.expr());
-            if res.error.is_some() {
-                return res;
-            }
+        if self.current_token.r#type != Tokens::RightSquareBraces {
+            return Err(Error::new(
+                "Invalid Syntax",
+                pos_start,
+                self.current_token.pos_end,
+                "Expected ']'",
+            ));
+        }
 
-            if self.current_token.r#type != Tokens::RightSquareBraces {
-                return res.failure(Error::new(
-                    "Invalid Syntax",
-                    pos_start,
-                    self.current_token.pos_end.clone(),
-                    "Expected ']'",
-                ));
-            }
+        res.register_advancement();
+        self.advance();
 
+        if self.current_token.r#type == Tokens::Equals {
             res.register_advancement();
             self.advance();
 
-            return res.success(Node::ArrayAcess {
-                array: Box::new(atom.unwrap()),
+            let expr = res.register(self.expr());
+            if let Some(error) = res.error.clone() {
+                return Err(error);
+            }
+
+            return Ok(Node::ArrayIndexEdit {
+                array: Box::new(array),
                 index: Box::new(index.unwrap()),
+                new_val: Box::new(expr.unwrap()),
             });
-        } else if self.current_token.r#type == Tokens::LeftParenthesis {
-            let mut arg_nodes = vec![];
-            res.register_advancement();
-            self.advance();
-            if self.current_token.r#type == Tokens::RightParenthesis {
-                res.register_advancement();
-                self.advance();
-            } else {
-                let expr = res.register(self.expr());
-                if res.error.is_some() {
-                    return res.failure(Error::new(
-                        "Invalid Syntax",
-                        self.current_token.pos_start.clone(),
-                        self.current_token.pos_end.clone(),
-                        "Expected ')', 'var', int, float, identifier, '+', '-' or ','",
-                    ));
-                }
-                arg_nodes.push(expr.unwrap());
+        }
 
-                while self.current_token.r#type == Tokens::Comma {
-                    res.register_advancement();
-                    self.advance();
+        Ok(Node::ArrayAcess {
+            array: Box::new(array),
+            index: Box::new(index.unwrap()),
+        })
+    }
 
-                    let expr = res.register(self.expr());
-                    if res.error.is_some() {
-                        return res.failure(Error::new(
-                            "Invalid Syntax",
-                            self.current_token.pos_start.clone(),
-                            self.current_token.pos_end.clone(),
-                            "Expected ')', 'var', int, float, identifier, '+', '-' or ','",
-                        ));
-                    }
-                    arg_nodes.push(expr.unwrap());
-                }
+    fn index_expr(&mut self) -> ParseResult {
+        let mut res = ParseResult::new();
+        let pos_start = self.current_token.pos_start.clone();
+        let atom = res.register(self.atom());
+        if res.error.is_some() {
+            return res;
+        }
 
-                if self.current_token.r#type != Tokens::RightParenthesis {
-                    return res.failure(Error::new(
-                        "Invalid Syntax",
-                        self.current_token.pos_start.clone(),
-                        self.current_token.pos_end.clone(),
-                        "Expected ')' or ','",
-                    ));
-                }
-                res.register_advancement();
-                self.advance();
-            }
+        if self.current_token.r#type == Tokens::LeftSquareBraces {
+            return match self.index_suffix(&mut res, atom.unwrap(), pos_start) {
+                Ok(node) => res.success(node),
+                Err(e) => res.failure(e),
+            };
+        } else if self.current_token.r#type == Tokens::LeftParenthesis {
+            let (arg_nodes, named) = match self.call_args(&mut res) {
+                Ok(args) => args,
+                Err(e) => return res.failure(e),
+            };
             return res.success(Node::CallNode {
                 node_to_call: Box::new(atom.clone().unwrap()),
                 args: arg_nodes,
+                named,
             });
         } else if self.current_token.r#type == Tokens::Dot {
             self.advance();
@@ -967,6 +1331,12 @@ impl Parser {
             return res.success(Node::BooleanNode {
                 token: token.clone(),
             });
+        } else if token.matches(Tokens::Keyword, DynType::String("null".to_string())) {
+            res.register_advancement();
+            self.advance();
+            return res.success(Node::NullNode {
+                token: token.clone(),
+            });
         } else if token.r#type == Tokens::String {
             res.register_advancement();
             self.advance();
@@ -979,6 +1349,10 @@ impl Parser {
             return res.success(Node::CharNode {
                 token: token.clone(),
             });
+        } else if self.is_contextual("delete")
+            && self.tokens.get(self.token_index + 1).map(|t| t.r#type) == Some(Tokens::Identifier)
+        {
+            return self.delete_expr();
         } else if token.r#type == Tokens::Identifier {
             res.register_advancement();
             self.advance();
@@ -1001,6 +1375,32 @@ impl Parser {
             return res.success(Node::VarAccessNode {
                 token: token.clone(),
             });
+        } else if token.matches(Tokens::Keyword, DynType::String("super".to_string())) {
+            res.register_advancement();
+            self.advance();
+
+            let node = Node::VarAccessNode {
+                token: token.clone(),
+            };
+            if self.current_token.r#type == Tokens::LeftParenthesis {
+                // super(...) calls the parent constructor, which is kept on the super object
+                return res.success(Node::ObjectPropAccess {
+                    object: Box::new(node),
+                    property: Token::new(
+                        Tokens::Identifier,
+                        token.pos_start,
+                        token.pos_end,
+                        DynType::String(String::from("@constructor")),
+                    ),
+                });
+            }
+            return res.success(node);
+        } else if token.r#type == Tokens::LeftParenthesis && self.is_lambda() {
+            let lambda = res.register(self.fun_signature());
+            if res.error.is_some() {
+                return res;
+            }
+            return res.success(lambda.unwrap());
         } else if token.r#type == Tokens::LeftParenthesis {
             res.register_advancement();
             self.advance();
@@ -1032,6 +1432,12 @@ impl Parser {
                 return res;
             }
             return res.success(obj_expr.unwrap());
+        } else if token.r#type == Tokens::Hash {
+            let map_expr = res.register(self.map_expr());
+            if res.error.is_some() {
+                return res;
+            }
+            return res.success(map_expr.unwrap());
         } else if token
             .clone()
             .matches(Tokens::Keyword, DynType::String("if".to_string()))
@@ -1059,6 +1465,24 @@ impl Parser {
                 return res;
             }
             return res.success(for_expr.unwrap());
+        } else if token
+            .clone()
+            .matches(Tokens::Keyword, DynType::String("match".to_string()))
+        {
+            let match_expr = res.register(self.match_expr());
+            if res.error.is_some() {
+                return res;
+            }
+            return res.success(match_expr.unwrap());
+        } else if token
+            .clone()
+            .matches(Tokens::Keyword, DynType::String("try".to_string()))
+        {
+            let try_expr = res.register(self.try_expr());
+            if res.error.is_some() {
+                return res;
+            }
+            return res.success(try_expr.unwrap());
         } else if token
             .clone()
             .matches(Tokens::Keyword, DynType::String("fun".to_string()))
@@ -1077,6 +1501,15 @@ impl Parser {
                 return res;
             }
             return res.success(class_def.unwrap());
+        } else if token
+            .clone()
+            .matches(Tokens::Keyword, DynType::String("enum".to_string()))
+        {
+            let enum_def = res.register(self.enum_def());
+            if res.error.is_some() {
+                return res;
+            }
+            return res.success(enum_def.unwrap());
         } else if token
             .clone()
             .matches(Tokens::Keyword, DynType::String("new".to_string()))
@@ -1096,6 +1529,25 @@ impl Parser {
         ))
     }
 
+    // Looks past the matching ')' for a '=>'
+    fn is_lambda(&self) -> bool {
+        let mut depth = 0;
+        for (i, token) in self.tokens.iter().enumerate().skip(self.token_index) {
+            match token.r#type {
+                Tokens::LeftParenthesis => depth += 1,
+                Tokens::RightParenthesis => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return self.tokens.get(i + 1).map(|t| t.r#type) == Some(Tokens::Arrow);
+                    }
+                }
+                Tokens::EOF => return false,
+                _ => (),
+            }
+        }
+        false
+    }
+
     fn obj_expr(&mut self) -> ParseResult {
         let mut res = ParseResult::new();
         let pos_start = self.current_token.clone().pos_start;
@@ -1234,6 +1686,79 @@ impl Parser {
         res.success(Node::ObjectDefNode { properties })
     }
 
+    // `#{key: value, ...}`, unlike in objects the keys are expressions evaluated at runtime
+    fn map_expr(&mut self) -> ParseResult {
+        let mut res = ParseResult::new();
+        let pos_start = self.current_token.pos_start;
+        let mut entries = vec![];
+
+        res.register_advancement();
+        self.advance();
+        if self.current_token.r#type != Tokens::LeftCurlyBraces {
+            return res.failure(Error::new(
+                "Invalid syntax",
+                pos_start,
+                self.current_token.pos_end,
+                "'{' was expected.",
+            ));
+        }
+        res.register_advancement();
+        self.advance();
+
+        loop {
+            if self.current_token.r#type == Tokens::Newline {
+                res.register_advancement();
+                self.advance();
+            }
+            if self.current_token.r#type == Tokens::RightCurlyBraces {
+                break;
+            }
+
+            let key = res.register(self.expr());
+            if res.error.is_some() {
+                return res;
+            }
+            if self.current_token.r#type != Tokens::Colon {
+                return res.failure(Error::new(
+                    "Invalid syntax",
+                    pos_start,
+                    self.current_token.pos_end,
+                    "':' was expected.",
+                ));
+            }
+            res.register_advancement();
+            self.advance();
+            let val = res.register(self.expr());
+            if res.error.is_some() {
+                return res;
+            }
+            entries.push((key.unwrap(), val.unwrap()));
+
+            if self.current_token.r#type != Tokens::Comma {
+                if self.current_token.r#type == Tokens::Newline {
+                    res.register_advancement();
+                    self.advance();
+                }
+                break;
+            }
+            res.register_advancement();
+            self.advance();
+        }
+
+        if self.current_token.r#type != Tokens::RightCurlyBraces {
+            return res.failure(Error::new(
+                "Invalid syntax",
+                pos_start,
+                self.current_token.pos_end,
+                "'}', ',' was expected.",
+            ));
+        }
+        res.register_advancement();
+        self.advance();
+
+        res.success(Node::MapNode { entries })
+    }
+
     fn array_expr(&mut self) -> ParseResult {
         let mut res = ParseResult::new();
         let mut element_nodes: Vec<Node> = vec![];
@@ -1297,7 +1822,10 @@ impl Parser {
         let mut res = ParseResult::new();
         let mut methods: Vec<(Token, Node)> = vec![];
         let mut properties: Vec<(Token, Node)> = vec![];
-        let mut constructor: Option<(Vec<Token>, Node)> = None;
+        let mut statics: Vec<(Token, Node)> = vec![];
+        let mut getters: Vec<(Token, Node)> = vec![];
+        let mut setters: Vec<(Token, Node)> = vec![];
+        let mut constructor: Option<Node> = None;
 
         if !self
             .current_token
@@ -1329,6 +1857,28 @@ impl Parser {
         res.register_advancement();
         self.advance();
 
+        let mut parent: Option<Token> = None;
+        if self
+            .current_token
+            .matches(Tokens::Keyword, DynType::String("extends".to_string()))
+        {
+            res.register_advancement();
+            self.advance();
+
+            if self.current_token.r#type != Tokens::Identifier {
+                return res.failure(Error::new(
+                    "Invalid Syntax",
+                    self.current_token.pos_start,
+                    self.current_token.pos_end,
+                    "Expected identifier",
+                ));
+            }
+            parent = Some(self.current_token.clone());
+
+            res.register_advancement();
+            self.advance();
+        }
+
         if self.current_token.r#type != Tokens::LeftCurlyBraces {
             return res.failure(Error::new(
                 "Invalid Syntax",
@@ -1349,6 +1899,86 @@ impl Parser {
             if self.current_token.r#type == Tokens::RightCurlyBraces {
                 break;
             }
+
+            if self
+                .current_token
+                .matches(Tokens::Keyword, DynType::String("static".to_string()))
+            {
+                res.register_advancement();
+                self.advance();
+
+                let member = res.register(self.expr());
+                if res.error.is_some() {
+                    return res;
+                }
+                let member = member.unwrap();
+                match &member {
+                    Node::VarAssignNode { name, value, .. } => {
+                        statics.push((name.clone(), *value.clone()))
+                    }
+                    Node::FunDef {
+                        name: Some(name), ..
+                    } => statics.push((name.clone(), member.clone())),
+                    _ => {
+                        return res.failure(Error::new(
+                            "Invalid Syntax",
+                            self.current_token.pos_start,
+                            self.current_token.pos_end,
+                            "Expected a property or method after 'static'",
+                        ))
+                    }
+                }
+                continue;
+            }
+
+            if self.current_token.r#type == Tokens::Identifier
+                && ["get", "set"].contains(&self.current_token.value.into_string().as_str())
+                && self.tokens[self.token_index + 1].r#type == Tokens::Identifier
+            {
+                let is_getter = self.current_token.value.into_string() == "get";
+                res.register_advancement();
+                self.advance();
+
+                let accessor = res.register(self.fun_signature());
+                if res.error.is_some() {
+                    return res;
+                }
+                if let Some(Node::FunDef {
+                    name: Some(name),
+                    body_node,
+                    arg_tokens,
+                    defaults,
+                    rest,
+                }) = accessor
+                {
+                    if arg_tokens.len() != if is_getter { 0 } else { 1 } || rest.is_some() {
+                        return res.failure(Error::new(
+                            "Invalid Syntax",
+                            name.pos_start,
+                            name.pos_end,
+                            if is_getter {
+                                "Getters should not take any parameters"
+                            } else {
+                                "Setters should take exactly one parameter"
+                            },
+                        ));
+                    }
+                    let accessor = Node::FunDef {
+                        name: None,
+                        body_node,
+                        arg_tokens,
+                        defaults,
+                        rest,
+                    };
+                    if is_getter {
+                        getters.push((name, accessor));
+                    } else {
+                        setters.push((name, accessor));
+                    }
+                }
+                continue;
+            }
+
             let stnts = res.register(self.expr());
             if res.error.is_some() {
                 return res;
@@ -1358,11 +1988,7 @@ impl Parser {
                 Node::VarAssignNode { name, value, .. } => {
                     properties.push((name.clone(), *value.clone()))
                 }
-                Node::FunDef {
-                    name,
-                    body_node,
-                    arg_tokens,
-                } => {
+                Node::FunDef { name, .. } => {
                     if name.as_ref().is_none() {
                         if constructor.is_some() {
                             return res.failure(Error::new(
@@ -1372,7 +1998,7 @@ impl Parser {
                                 "Constructor defined",
                             ));
                         }
-                        constructor = Some((arg_tokens, *body_node.clone()));
+                        constructor = Some(a);
                     } else {
                         methods.push((name.as_ref().unwrap().clone(), a));
                     }
@@ -1402,104 +2028,409 @@ impl Parser {
 
         res.success(Node::ClassDefNode {
             name,
+            parent,
             constructor: Box::new(constructor),
             properties,
             methods,
+            statics,
+            getters,
+            setters,
         })
     }
 
-    fn class_init(&mut self) -> ParseResult {
+    // Parses `import { a, b as c } from "path"` and `import * as name from "path"`
+    fn import_stmt(&mut self) -> ParseResult {
         let mut res = ParseResult::new();
-        let mut constructor_params: Vec<Node> = vec![];
+        res.register_advancement();
+        self.advance();
 
-        if !self
-            .current_token
-            .clone()
-            .matches(Tokens::Keyword, DynType::String("new".to_string()))
-        {
-            return res.failure(Error::new(
-                "Invalid Syntax",
-                self.current_token.pos_start.clone(),
-                self.current_token.pos_end.clone(),
-                "Expected 'new'",
+        let mut names = vec![];
+        let mut namespace = None;
+        if self.current_token.r#type == Tokens::Multiply {
+            res.register_advancement();
+            self.advance();
+            if !self.is_contextual("as")
+                || self.tokens.get(self.token_index + 1).map(|t| t.r#type)
+                    != Some(Tokens::Identifier)
+            {
+                return res.failure(Error::new(
+                    "Invalid Syntax",
+                    self.current_token.pos_start,
+                    self.current_token.pos_end,
+                    "Expected 'as' and an identifier",
+                ));
+            }
+            res.register_advancement();
+            self.advance();
+            namespace = Some(self.current_token.clone());
+            res.register_advancement();
+            self.advance();
+        } else if self.current_token.r#type == Tokens::LeftCurlyBraces {
+            res.register_advancement();
+            self.advance();
+            while self.current_token.r#type != Tokens::RightCurlyBraces {
+                if self.current_token.r#type != Tokens::Identifier {
+                    return res.failure(Error::new(
+                        "Invalid Syntax",
+                        self.current_token.pos_start,
+                        self.current_token.pos_end,
+                        "Expected identifier",
+                    ));
+                }
+                let name = self.current_token.clone();
+                let mut local = name.clone();
+                res.register_advancement();
+                self.advance();
+                if self.is_contextual("as") {
+                    res.register_advancement();
+                    self.advance();
+                    if self.current_token.r#type != Tokens::Identifier {
+                        return res.failure(Error::new(
+                            "Invalid Syntax",
+                            self.current_token.pos_start,
+                            self.current_token.pos_end,
+                            "Expected identifier",
+                        ));
+                    }
+                    local = self.current_token.clone();
+                    res.register_advancement();
+                    self.advance();
+                }
+                names.push((name, local));
+
+                if self.current_token.r#type == Tokens::Comma {
+                    res.register_advancement();
+                    self.advance();
+                } else if self.current_token.r#type != Tokens::RightCurlyBraces {
+                    return res.failure(Error::new(
+                        "Invalid Syntax",
+                        self.current_token.pos_start,
+                        self.current_token.pos_end,
+                        "Expected '}' or ','",
+                    ));
+                }
+            }
+            res.register_advancement();
+            self.advance();
+        } else {
+            return res.failure(Error::new(
+                "Invalid Syntax",
+                self.current_token.pos_start,
+                self.current_token.pos_end,
+                "Expected '{' or '*'",
             ));
         }
 
+        if !self.is_contextual("from") {
+            return res.failure(Error::new(
+                "Invalid Syntax",
+                self.current_token.pos_start,
+                self.current_token.pos_end,
+                "Expected 'from'",
+            ));
+        }
+        res.register_advancement();
+        self.advance();
+        if self.current_token.r#type != Tokens::String {
+            return res.failure(Error::new(
+                "Invalid Syntax",
+                self.current_token.pos_start,
+                self.current_token.pos_end,
+                "Expected the path of a module",
+            ));
+        }
+        let path = self.current_token.clone();
+        res.register_advancement();
+        self.advance();
+
+        res.success(Node::ImportNode {
+            path,
+            names,
+            namespace,
+        })
+    }
+
+    // `delete obj.key` or `delete obj[key]`, `delete` is only special when a name follows
+    // it so that it can still be called as a function
+    fn delete_expr(&mut self) -> ParseResult {
+        let mut res = ParseResult::new();
+        let token = self.current_token.clone();
+        res.register_advancement();
+        self.advance();
+
+        let target = res.register(self.call());
+        if res.error.is_some() {
+            return res;
+        }
+        match target.unwrap() {
+            target @ Node::ObjectPropAccess { .. } | target @ Node::ArrayAcess { .. } => res
+                .success(Node::DeleteNode {
+                    token,
+                    target: Box::new(target),
+                }),
+            _ => res.failure(Error::new(
+                "Invalid Syntax",
+                token.pos_start,
+                self.current_token.pos_start,
+                "Expected a property or an index after 'delete'",
+            )),
+        }
+    }
+
+    // Skips to `keyword` when only newlines come before it, so `else`, `catch` and
+    // `finally` can start a line of their own
+    fn skip_newlines_before(&mut self, res: &mut ParseResult, keyword: &str) {
+        let mut i = self.token_index;
+        while self
+            .tokens
+            .get(i)
+            .is_some_and(|t| t.r#type == Tokens::Newline)
+        {
+            i += 1;
+        }
+        let keyword = DynType::String(keyword.to_string());
+        if self
+            .tokens
+            .get(i)
+            .is_some_and(|t| t.matches(Tokens::Keyword, keyword))
+        {
+            while self.token_index < i {
+                res.register_advancement();
+                self.advance();
+            }
+        }
+    }
+
+    fn is_contextual(&self, word: &str) -> bool {
+        self.current_token.r#type == Tokens::Identifier
+            && self.current_token.value == DynType::String(word.to_string())
+    }
+
+    // Parses `(a, b, name: c)`, positional arguments have to come before the named ones
+    fn call_args(&mut self, res: &mut ParseResult) -> Result<CallArgs, Error> {
+        let mut args: Vec<Node> = vec![];
+        let mut named: Vec<(Token, Node)> = vec![];
+        res.register_advancement();
+        self.advance();
+
+        while self.current_token.r#type != Tokens::RightParenthesis {
+            let mut name = None;
+            if self.current_token.r#type == Tokens::Identifier
+                && self.tokens.get(self.token_index + 1).map(|t| t.r#type) == Some(Tokens::Colon)
+            {
+                name = Some(self.current_token.clone());
+                res.register_advancement();
+                self.advance();
+                res.register_advancement();
+                self.advance();
+            }
+
+            let expr = res.register(self.expr());
+            if res.error.is_some() {
+                return Err(Error::new(
+                    "Invalid Syntax",
+                    self.current_token.pos_start,
+                    self.current_token.pos_end,
+                    "Expected ')', 'var', int, float, identifier, '+', '-' or ','",
+                ));
+            }
+            match name {
+                Some(name) => named.push((name, expr.unwrap())),
+                None if !named.is_empty() => {
+                    return Err(Error::new(
+                        "Invalid Syntax",
+                        self.current_token.pos_start,
+                        self.current_token.pos_end,
+                        "Positional arguments should come before named ones",
+                    ))
+                }
+                None => args.push(expr.unwrap()),
+            }
+
+            if self.current_token.r#type == Tokens::Comma {
+                res.register_advancement();
+                self.advance();
+            } else if self.current_token.r#type != Tokens::RightParenthesis {
+                return Err(Error::new(
+                    "Invalid Syntax",
+                    self.current_token.pos_start,
+                    self.current_token.pos_end,
+                    "Expected ')' or ','",
+                ));
+            }
+        }
+        res.register_advancement();
+        self.advance();
+
+        Ok((args, named))
+    }
+
+    fn enum_def(&mut self) -> ParseResult {
+        let mut res = ParseResult::new();
+        let mut variants: Vec<(Token, Vec<Token>)> = vec![];
+
         res.register_advancement();
         self.advance();
 
         if self.current_token.r#type != Tokens::Identifier {
             return res.failure(Error::new(
                 "Invalid Syntax",
-                self.current_token.pos_start.clone(),
-                self.current_token.pos_end.clone(),
+                self.current_token.pos_start,
+                self.current_token.pos_end,
                 "Expected identifier",
             ));
         }
-
         let name = self.current_token.clone();
 
         res.register_advancement();
         self.advance();
 
-        if self.current_token.r#type == Tokens::LeftParenthesis {
+        if self.current_token.r#type != Tokens::LeftCurlyBraces {
+            return res.failure(Error::new(
+                "Invalid Syntax",
+                self.current_token.pos_start,
+                self.current_token.pos_end,
+                "Expected '{'",
+            ));
+        }
+
+        res.register_advancement();
+        self.advance();
+
+        loop {
+            while [Tokens::Newline, Tokens::Comma].contains(&self.current_token.r#type) {
+                res.register_advancement();
+                self.advance();
+            }
+            if self.current_token.r#type == Tokens::RightCurlyBraces {
+                break;
+            }
+
+            if self.current_token.r#type != Tokens::Identifier {
+                return res.failure(Error::new(
+                    "Invalid Syntax",
+                    self.current_token.pos_start,
+                    self.current_token.pos_end,
+                    "Expected a variant name",
+                ));
+            }
+            let variant = self.current_token.clone();
+            if variants.iter().any(|(v, _)| v.value == variant.value) {
+                return res.failure(Error::new(
+                    "Invalid Syntax",
+                    variant.pos_start,
+                    variant.pos_end,
+                    "Variant is already defined",
+                ));
+            }
+
             res.register_advancement();
             self.advance();
 
-            if self.current_token.r#type == Tokens::RightParenthesis {
+            let mut fields: Vec<Token> = vec![];
+            if self.current_token.r#type == Tokens::LeftParenthesis {
                 res.register_advancement();
                 self.advance();
-            } else {
-                let expr = res.register(self.expr());
-                if res.error.is_some() {
-                    return res.failure(Error::new(
-                        "Invalid Syntax",
-                        self.current_token.pos_start.clone(),
-                        self.current_token.pos_end.clone(),
-                        "Expected ')', 'var', int, float, identifier, '+', '-' or ','",
-                    ));
-                }
-                constructor_params.push(expr.unwrap());
 
-                while self.current_token.r#type == Tokens::Comma {
+                while self.current_token.r#type != Tokens::RightParenthesis {
+                    if self.current_token.r#type != Tokens::Identifier {
+                        return res.failure(Error::new(
+                            "Invalid Syntax",
+                            self.current_token.pos_start,
+                            self.current_token.pos_end,
+                            "Expected identifier",
+                        ));
+                    }
+                    fields.push(self.current_token.clone());
                     res.register_advancement();
                     self.advance();
 
-                    let expr = res.register(self.expr());
-                    if res.error.is_some() {
+                    if self.current_token.r#type == Tokens::Comma {
+                        res.register_advancement();
+                        self.advance();
+                    } else if self.current_token.r#type != Tokens::RightParenthesis {
                         return res.failure(Error::new(
                             "Invalid Syntax",
-                            self.current_token.pos_start.clone(),
-                            self.current_token.pos_end.clone(),
-                            "Expected ')', 'var', int, float, identifier, '+', '-' or ','",
+                            self.current_token.pos_start,
+                            self.current_token.pos_end,
+                            "Expected ')' or ','",
                         ));
                     }
-                    constructor_params.push(expr.unwrap());
                 }
 
-                if self.current_token.r#type != Tokens::RightParenthesis {
-                    return res.failure(Error::new(
-                        "Invalid Syntax",
-                        self.current_token.pos_start.clone(),
-                        self.current_token.pos_end.clone(),
-                        "Expected ')' or ','",
-                    ));
-                }
                 res.register_advancement();
                 self.advance();
             }
-        } else {
+            variants.push((variant, fields));
+
+            if ![Tokens::Newline, Tokens::Comma, Tokens::RightCurlyBraces]
+                .contains(&self.current_token.r#type)
+            {
+                return res.failure(Error::new(
+                    "Invalid Syntax",
+                    self.current_token.pos_start,
+                    self.current_token.pos_end,
+                    "Expected '}' or ','",
+                ));
+            }
+        }
+
+        res.register_advancement();
+        self.advance();
+
+        res.success(Node::EnumDefNode { name, variants })
+    }
+
+    fn class_init(&mut self) -> ParseResult {
+        let mut res = ParseResult::new();
+
+        if !self
+            .current_token
+            .clone()
+            .matches(Tokens::Keyword, DynType::String("new".to_string()))
+        {
+            return res.failure(Error::new(
+                "Invalid Syntax",
+                self.current_token.pos_start.clone(),
+                self.current_token.pos_end.clone(),
+                "Expected 'new'",
+            ));
+        }
+
+        res.register_advancement();
+        self.advance();
+
+        if self.current_token.r#type != Tokens::Identifier {
             return res.failure(Error::new(
                 "Invalid Syntax",
                 self.current_token.pos_start.clone(),
                 self.current_token.pos_end.clone(),
+                "Expected identifier",
+            ));
+        }
+
+        let name = self.current_token.clone();
+
+        res.register_advancement();
+        self.advance();
+
+        if self.current_token.r#type != Tokens::LeftParenthesis {
+            return res.failure(Error::new(
+                "Invalid Syntax",
+                self.current_token.pos_start,
+                self.current_token.pos_end,
                 "Expected '('",
             ));
         }
+        let (constructor_params, named) = match self.call_args(&mut res) {
+            Ok(args) => args,
+            Err(e) => return res.failure(e),
+        };
 
         res.success(Node::ClassInitNode {
             name,
             constructor_params,
+            named,
         })
     }
 
@@ -1567,6 +2498,7 @@ impl Parser {
         self.advance();
         res.register_advancement();
 
+        self.skip_newlines_before(&mut res, "else");
         while self
             .current_token
             .clone()
@@ -1625,6 +2557,7 @@ impl Parser {
                 }
                 res.register_advancement();
                 self.advance();
+                self.skip_newlines_before(&mut res, "else");
             } else {
                 if !self
                     .current_token
@@ -1733,6 +2666,7 @@ impl Parser {
         res.success(Node::WhileNode {
             condition_node: Box::new(condition_node.clone().unwrap()),
             body_node: Box::new(body_node.clone().unwrap()),
+            label: None,
         })
     }
 
@@ -1767,17 +2701,72 @@ impl Parser {
         res.register_advancement();
         self.advance();
 
-        if self.current_token.r#type != Tokens::Equals {
-            return res.failure(Error::new(
-                "Invalid Syntax",
-                self.current_token.pos_start.clone(),
-                self.current_token.pos_end.clone(),
-                "Expected '='",
-            ));
-        }
+        if self
+            .current_token
+            .matches(Tokens::Keyword, DynType::String("in".to_string()))
+        {
+            res.register_advancement();
+            self.advance();
 
-        res.register_advancement();
-        self.advance();
+            let iterable = res.register(self.expr());
+            if res.error.is_some() {
+                return res;
+            }
+
+            if !self
+                .current_token
+                .matches(Tokens::LeftCurlyBraces, DynType::None)
+            {
+                return res.failure(Error::new(
+                    "Invalid Syntax",
+                    self.current_token.pos_start,
+                    self.current_token.pos_end,
+                    "Expected '{'",
+                ));
+            }
+
+            res.register_advancement();
+            self.advance();
+
+            let body = res.register(self.statements());
+            if res.error.is_some() {
+                return res;
+            }
+
+            if !self
+                .current_token
+                .matches(Tokens::RightCurlyBraces, DynType::None)
+            {
+                return res.failure(Error::new(
+                    "Invalid Syntax",
+                    self.current_token.pos_start,
+                    self.current_token.pos_end,
+                    "Expected '}'",
+                ));
+            }
+
+            res.register_advancement();
+            self.advance();
+
+            return res.success(Node::ForInNode {
+                var_name_token: var_name,
+                iterable: Box::new(iterable.unwrap()),
+                body_node: Box::new(body.unwrap()),
+                label: None,
+            });
+        }
+
+        if self.current_token.r#type != Tokens::Equals {
+            return res.failure(Error::new(
+                "Invalid Syntax",
+                self.current_token.pos_start.clone(),
+                self.current_token.pos_end.clone(),
+                "Expected '=' or 'in'",
+            ));
+        }
+
+        res.register_advancement();
+        self.advance();
 
         let init_expr = res.register(self.expr());
         if res.error.is_some() {
@@ -1869,6 +2858,446 @@ impl Parser {
             end_value: Box::new(end_expr.clone().unwrap()),
             body_node: Box::new(body.clone().unwrap()),
             step_value_node: Box::new(step.clone()),
+            label: None,
+        })
+    }
+
+    fn block(&mut self) -> ParseResult {
+        let mut res = ParseResult::new();
+        if !self
+            .current_token
+            .clone()
+            .matches(Tokens::LeftCurlyBraces, DynType::None)
+        {
+            return res.failure(Error::new(
+                "Invalid Syntax",
+                self.current_token.pos_start,
+                self.current_token.pos_end,
+                "Expected '{'",
+            ));
+        }
+
+        res.register_advancement();
+        self.advance();
+
+        let body = res.register(self.statements());
+        if res.error.is_some() {
+            return res;
+        }
+
+        if !self
+            .current_token
+            .clone()
+            .matches(Tokens::RightCurlyBraces, DynType::None)
+        {
+            return res.failure(Error::new(
+                "Invalid Syntax",
+                self.current_token.pos_start,
+                self.current_token.pos_end,
+                "Expected '}'",
+            ));
+        }
+
+        res.register_advancement();
+        self.advance();
+
+        res.success(body.unwrap())
+    }
+
+    fn match_expr(&mut self) -> ParseResult {
+        let mut res = ParseResult::new();
+        res.register_advancement();
+        self.advance();
+
+        let value = res.register(self.expr());
+        if res.error.is_some() {
+            return res;
+        }
+        if self.current_token.r#type != Tokens::LeftCurlyBraces {
+            return res.failure(Error::new(
+                "Invalid Syntax",
+                self.current_token.pos_start,
+                self.current_token.pos_end,
+                "Expected '{'",
+            ));
+        }
+        res.register_advancement();
+        self.advance();
+
+        let mut arms = vec![];
+        loop {
+            while [Tokens::Newline, Tokens::Comma].contains(&self.current_token.r#type) {
+                res.register_advancement();
+                self.advance();
+            }
+            if self.current_token.r#type == Tokens::RightCurlyBraces {
+                break;
+            }
+
+            let pattern = match self.pattern(&mut res) {
+                Ok(pattern) => pattern,
+                Err(e) => return res.failure(e),
+            };
+            let mut guard = None;
+            if self
+                .current_token
+                .matches(Tokens::Keyword, DynType::String("if".to_string()))
+            {
+                res.register_advancement();
+                self.advance();
+                guard = res.register(self.expr());
+                if res.error.is_some() {
+                    return res;
+                }
+            }
+            if self.current_token.r#type != Tokens::Arrow {
+                return res.failure(Error::new(
+                    "Invalid Syntax",
+                    self.current_token.pos_start,
+                    self.current_token.pos_end,
+                    "Expected '=>'",
+                ));
+            }
+            res.register_advancement();
+            self.advance();
+
+            let body = if self.current_token.r#type == Tokens::LeftCurlyBraces {
+                res.register(self.block())
+            } else {
+                res.register(self.expr())
+            };
+            if res.error.is_some() {
+                return res;
+            }
+            arms.push((pattern, guard, body.unwrap()));
+
+            if ![Tokens::Newline, Tokens::Comma, Tokens::RightCurlyBraces]
+                .contains(&self.current_token.r#type)
+            {
+                return res.failure(Error::new(
+                    "Invalid Syntax",
+                    self.current_token.pos_start,
+                    self.current_token.pos_end,
+                    "Expected ',', '}' or a new line",
+                ));
+            }
+        }
+        if arms.is_empty() {
+            return res.failure(Error::new(
+                "Invalid Syntax",
+                self.current_token.pos_start,
+                self.current_token.pos_end,
+                "Expected at least one arm",
+            ));
+        }
+        res.register_advancement();
+        self.advance();
+
+        res.success(Node::MatchNode {
+            value: Box::new(value.unwrap()),
+            arms,
+        })
+    }
+
+    // Parses `a | b`, where each alternative is a literal, `_`, a name to bind,
+    // `[a, b, ...rest]` or `{"key": a, b}`
+    fn pattern(&mut self, res: &mut ParseResult) -> Result<Pattern, Error> {
+        let mut alternatives = vec![self.single_pattern(res)?];
+        while self.current_token.r#type == Tokens::BitOr {
+            res.register_advancement();
+            self.advance();
+            alternatives.push(self.single_pattern(res)?);
+        }
+        Ok(if alternatives.len() == 1 {
+            alternatives.pop().unwrap()
+        } else {
+            Pattern::Or(alternatives)
+        })
+    }
+
+    fn single_pattern(&mut self, res: &mut ParseResult) -> Result<Pattern, Error> {
+        let token = self.current_token.clone();
+        res.register_advancement();
+        self.advance();
+
+        match token.r#type {
+            Tokens::Identifier if token.value == DynType::String("_".to_string()) => {
+                Ok(Pattern::Wildcard(token))
+            }
+            Tokens::Identifier if self.current_token.r#type == Tokens::Dot => {
+                res.register_advancement();
+                self.advance();
+                let variant = self.current_token.clone();
+                if variant.r#type != Tokens::Identifier {
+                    return Err(Error::new(
+                        "Invalid Syntax",
+                        variant.pos_start,
+                        variant.pos_end,
+                        "Expected a variant name",
+                    ));
+                }
+                res.register_advancement();
+                self.advance();
+
+                let mut fields = vec![];
+                if self.current_token.r#type == Tokens::LeftParenthesis {
+                    res.register_advancement();
+                    self.advance();
+                    while self.current_token.r#type != Tokens::RightParenthesis {
+                        fields.push(self.pattern(res)?);
+                        if self.current_token.r#type == Tokens::Comma {
+                            res.register_advancement();
+                            self.advance();
+                        } else if self.current_token.r#type != Tokens::RightParenthesis {
+                            return Err(Error::new(
+                                "Invalid Syntax",
+                                self.current_token.pos_start,
+                                self.current_token.pos_end,
+                                "Expected ')' or ','",
+                            ));
+                        }
+                    }
+                    res.register_advancement();
+                    self.advance();
+                }
+                Ok(Pattern::Variant(token, variant, fields))
+            }
+            Tokens::Identifier => Ok(Pattern::Binding(token)),
+            Tokens::Int | Tokens::Float | Tokens::String | Tokens::Char | Tokens::Boolean => {
+                Ok(Pattern::Literal(token))
+            }
+            Tokens::Keyword if token.value == DynType::String("null".to_string()) => {
+                Ok(Pattern::Literal(token))
+            }
+            Tokens::Minus => {
+                let mut number = self.current_token.clone();
+                number.value = match number.value {
+                    DynType::Int(i) => match i.checked_neg() {
+                        Some(i) => DynType::Int(i),
+                        None => DynType::BigInt(BigInt::from_i128(i).neg()),
+                    },
+                    DynType::BigInt(i) => DynType::BigInt(i.neg()),
+                    DynType::Float(f) => DynType::Float(-f),
+                    _ => {
+                        return Err(Error::new(
+                            "Invalid Syntax",
+                            number.pos_start,
+                            number.pos_end,
+                            "Expected a number",
+                        ))
+                    }
+                };
+                number.pos_start = token.pos_start;
+                res.register_advancement();
+                self.advance();
+                Ok(Pattern::Literal(number))
+            }
+            Tokens::LeftSquareBraces => {
+                let mut elements = vec![];
+                let mut rest = None;
+                while self.current_token.r#type != Tokens::RightSquareBraces {
+                    if self.current_token.r#type == Tokens::TripleDot {
+                        res.register_advancement();
+                        self.advance();
+                        if self.current_token.r#type != Tokens::Identifier {
+                            return Err(Error::new(
+                                "Invalid Syntax",
+                                self.current_token.pos_start,
+                                self.current_token.pos_end,
+                                "Expected identifier",
+                            ));
+                        }
+                        rest = Some(self.current_token.clone());
+                        res.register_advancement();
+                        self.advance();
+                        if self.current_token.r#type != Tokens::RightSquareBraces {
+                            return Err(Error::new(
+                                "Invalid Syntax",
+                                self.current_token.pos_start,
+                                self.current_token.pos_end,
+                                "Rest pattern should be the last one",
+                            ));
+                        }
+                        break;
+                    }
+
+                    elements.push(self.pattern(res)?);
+                    if self.current_token.r#type == Tokens::Comma {
+                        res.register_advancement();
+                        self.advance();
+                    } else if self.current_token.r#type != Tokens::RightSquareBraces {
+                        return Err(Error::new(
+                            "Invalid Syntax",
+                            self.current_token.pos_start,
+                            self.current_token.pos_end,
+                            "Expected ']' or ','",
+                        ));
+                    }
+                }
+                res.register_advancement();
+                self.advance();
+                Ok(Pattern::Array(token, elements, rest))
+            }
+            Tokens::LeftCurlyBraces => {
+                let mut entries = vec![];
+                while self.current_token.r#type != Tokens::RightCurlyBraces {
+                    let key = self.current_token.clone();
+                    if ![Tokens::Identifier, Tokens::String].contains(&key.r#type) {
+                        return Err(Error::new(
+                            "Invalid Syntax",
+                            key.pos_start,
+                            key.pos_end,
+                            "Expected a property name",
+                        ));
+                    }
+                    res.register_advancement();
+                    self.advance();
+
+                    let pattern = if self.current_token.r#type == Tokens::Colon {
+                        res.register_advancement();
+                        self.advance();
+                        self.pattern(res)?
+                    } else if key.r#type == Tokens::Identifier {
+                        Pattern::Binding(key.clone())
+                    } else {
+                        return Err(Error::new(
+                            "Invalid Syntax",
+                            self.current_token.pos_start,
+                            self.current_token.pos_end,
+                            "Expected ':'",
+                        ));
+                    };
+                    entries.push((key, pattern));
+
+                    if self.current_token.r#type == Tokens::Comma {
+                        res.register_advancement();
+                        self.advance();
+                    } else if self.current_token.r#type != Tokens::RightCurlyBraces {
+                        return Err(Error::new(
+                            "Invalid Syntax",
+                            self.current_token.pos_start,
+                            self.current_token.pos_end,
+                            "Expected '}' or ','",
+                        ));
+                    }
+                }
+                res.register_advancement();
+                self.advance();
+                Ok(Pattern::Object(token, entries))
+            }
+            _ => Err(Error::new(
+                "Invalid Syntax",
+                token.pos_start,
+                token.pos_end,
+                "Expected a pattern",
+            )),
+        }
+    }
+
+    fn try_expr(&mut self) -> ParseResult {
+        let mut res = ParseResult::new();
+        let try_token = self.current_token.clone();
+        if !try_token.matches(Tokens::Keyword, DynType::String("try".to_string())) {
+            return res.failure(Error::new(
+                "Invalid Syntax",
+                try_token.pos_start,
+                try_token.pos_end,
+                "Expected 'try'",
+            ));
+        }
+
+        res.register_advancement();
+        self.advance();
+
+        let try_body = res.register(self.block());
+        if res.error.is_some() {
+            return res;
+        }
+
+        let mut catch_var: Option<Token> = None;
+        let mut catch_body: Option<Node> = None;
+        self.skip_newlines_before(&mut res, "catch");
+        if self
+            .current_token
+            .clone()
+            .matches(Tokens::Keyword, DynType::String("catch".to_string()))
+        {
+            res.register_advancement();
+            self.advance();
+
+            if self.current_token.r#type != Tokens::LeftParenthesis {
+                return res.failure(Error::new(
+                    "Invalid Syntax",
+                    self.current_token.pos_start,
+                    self.current_token.pos_end,
+                    "Expected '('",
+                ));
+            }
+
+            res.register_advancement();
+            self.advance();
+
+            if self.current_token.r#type != Tokens::Identifier {
+                return res.failure(Error::new(
+                    "Invalid Syntax",
+                    self.current_token.pos_start,
+                    self.current_token.pos_end,
+                    "Expected Identifier",
+                ));
+            }
+            catch_var = Some(self.current_token.clone());
+
+            res.register_advancement();
+            self.advance();
+
+            if self.current_token.r#type != Tokens::RightParenthesis {
+                return res.failure(Error::new(
+                    "Invalid Syntax",
+                    self.current_token.pos_start,
+                    self.current_token.pos_end,
+                    "Expected ')'",
+                ));
+            }
+
+            res.register_advancement();
+            self.advance();
+
+            catch_body = res.register(self.block());
+            if res.error.is_some() {
+                return res;
+            }
+        }
+
+        let mut finally_body: Option<Node> = None;
+        self.skip_newlines_before(&mut res, "finally");
+        if self
+            .current_token
+            .clone()
+            .matches(Tokens::Keyword, DynType::String("finally".to_string()))
+        {
+            res.register_advancement();
+            self.advance();
+
+            finally_body = res.register(self.block());
+            if res.error.is_some() {
+                return res;
+            }
+        }
+
+        if catch_body.is_none() && finally_body.is_none() {
+            return res.failure(Error::new(
+                "Invalid Syntax",
+                self.current_token.pos_start,
+                self.current_token.pos_end,
+                "Expected 'catch' or 'finally'",
+            ));
+        }
+
+        res.success(Node::TryNode {
+            try_body: Box::new(try_body.unwrap()),
+            catch_var,
+            catch_body: Box::new(catch_body),
+            finally_body: Box::new(finally_body),
         })
     }
 
@@ -1890,6 +3319,15 @@ impl Parser {
         res.register_advancement();
         self.advance();
 
+        let fun_def = res.register(self.fun_signature());
+        if res.error.is_some() {
+            return res;
+        }
+        res.success(fun_def.unwrap())
+    }
+
+    fn fun_signature(&mut self) -> ParseResult {
+        let mut res = ParseResult::new();
         let mut fun_name: Option<Token> = None;
         if self.current_token.r#type == Tokens::Identifier {
             fun_name = Some(self.current_token.clone());
@@ -1918,47 +3356,77 @@ impl Parser {
         self.advance();
 
         let mut args_name_tokens: Vec<Token> = vec![];
-        if self.current_token.r#type == Tokens::Identifier {
-            let name = self.current_token.clone();
-            args_name_tokens.push(name);
-
-            res.register_advancement();
-            self.advance();
-
-            while self.current_token.r#type == Tokens::Comma {
+        let mut defaults: Vec<Option<Node>> = vec![];
+        let mut rest: Option<Token> = None;
+        while self.current_token.r#type != Tokens::RightParenthesis {
+            if self.current_token.r#type == Tokens::TripleDot {
                 res.register_advancement();
                 self.advance();
-
-                if self.current_token.r#type == Tokens::Identifier {
-                    let new_arg_token = self.current_token.clone();
-                    args_name_tokens.push(new_arg_token);
-                    res.register_advancement();
-                    self.advance();
-                } else {
+                if self.current_token.r#type != Tokens::Identifier {
                     return res.failure(Error::new(
                         "Invalid Syntax",
-                        self.current_token.pos_start.clone(),
-                        self.current_token.pos_end.clone(),
+                        self.current_token.pos_start,
+                        self.current_token.pos_end,
                         "Expected Identifier",
                     ));
                 }
+                rest = Some(self.current_token.clone());
+                res.register_advancement();
+                self.advance();
+                if self.current_token.r#type != Tokens::RightParenthesis {
+                    return res.failure(Error::new(
+                        "Invalid Syntax",
+                        self.current_token.pos_start,
+                        self.current_token.pos_end,
+                        "Rest parameter should be the last one",
+                    ));
+                }
+                break;
             }
 
-            if self.current_token.r#type != Tokens::RightParenthesis {
+            if self.current_token.r#type != Tokens::Identifier {
                 return res.failure(Error::new(
                     "Invalid Syntax",
-                    self.current_token.pos_start.clone(),
-                    self.current_token.pos_end.clone(),
+                    self.current_token.pos_start,
+                    self.current_token.pos_end,
+                    "Expected ')', '...' or identifier",
+                ));
+            }
+            let name = self.current_token.clone();
+            res.register_advancement();
+            self.advance();
+
+            if self.current_token.r#type == Tokens::Equals {
+                res.register_advancement();
+                self.advance();
+                let default = res.register(self.expr());
+                if res.error.is_some() {
+                    return res;
+                }
+                defaults.push(default);
+            } else if defaults.iter().any(|d| d.is_some()) {
+                return res.failure(Error::new(
+                    "Invalid Syntax",
+                    name.pos_start,
+                    name.pos_end,
+                    "Parameters without a default value should come before the ones with it",
+                ));
+            } else {
+                defaults.push(None);
+            }
+            args_name_tokens.push(name);
+
+            if self.current_token.r#type == Tokens::Comma {
+                res.register_advancement();
+                self.advance();
+            } else if self.current_token.r#type != Tokens::RightParenthesis {
+                return res.failure(Error::new(
+                    "Invalid Syntax",
+                    self.current_token.pos_start,
+                    self.current_token.pos_end,
                     "Expected ')' or ','",
                 ));
             }
-        } else if self.current_token.r#type != Tokens::RightParenthesis {
-            return res.failure(Error::new(
-                "Invalid Syntax",
-                self.current_token.pos_start.clone(),
-                self.current_token.pos_end.clone(),
-                "Expected ')' or identifier",
-            ));
         }
 
         res.register_advancement();
@@ -1981,12 +3449,22 @@ impl Parser {
             .clone()
             .matches(Tokens::LeftCurlyBraces, DynType::None)
         {
-            return res.failure(Error::new(
-                "Invalid Syntax",
-                self.current_token.pos_start.clone(),
-                self.current_token.pos_end.clone(),
-                "Expected '{'",
-            ));
+            let expr = res.register(self.expr());
+            if res.error.is_some() {
+                return res;
+            }
+
+            return res.success(Node::FunDef {
+                name: fun_name,
+                body_node: Box::new(Node::Statements {
+                    statements: vec![Node::ReturnNode {
+                        value: Box::new(expr),
+                    }],
+                }),
+                arg_tokens: args_name_tokens,
+                defaults,
+                rest,
+            });
         }
         self.advance();
         res.register_advancement();
@@ -2015,6 +3493,8 @@ impl Parser {
             name: fun_name,
             body_node: Box::new(body_node.clone().unwrap()),
             arg_tokens: args_name_tokens,
+            defaults,
+            rest,
         })
     }
 }